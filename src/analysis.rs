@@ -1,2 +1,8 @@
+pub mod charm_exposure;
+pub mod delta_exposure;
 pub mod gamma_exposure;
+pub mod gex_cache;
+pub mod indicators;
 pub mod option_stats;
+pub mod vanna_exposure;
+pub mod vol_premium;
@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+
+use chrono::Local;
+
+use crate::{
+    math::bs::charm,
+    types::{GammaExposureStats, OptionInfo, OptionType},
+};
+
+use super::gamma_exposure::{parse_date, GammaExposureOptions, StrikeExposure};
+
+/// Tau is calendar days to the expiration date over 365, so charm comes out
+/// per year. Options expiring today have no whole days left and are treated
+/// as having one hour remaining instead of being dropped.
+const EXPIRATION_DAY_TAU: f64 = 1.0 / (365.0 * 24.0);
+
+pub fn charm_exposure_by_price(
+    option_chain: &[OptionInfo],
+    current_price: f64,
+    options: &GammaExposureOptions,
+) -> anyhow::Result<BTreeMap<String, StrikeExposure>> {
+    let now = Local::now().date_naive();
+    let mut strike_to_charm_exposure: BTreeMap<String, StrikeExposure> = BTreeMap::new();
+
+    for option in option_chain.iter().filter(|o| options.includes(o)) {
+        let expiration_date = parse_date(&option.expiration_date)?;
+        let days_remaining = expiration_date.signed_duration_since(now).num_days();
+        let tau = match days_remaining {
+            d if d < 0 => 0.0,
+            0 => EXPIRATION_DAY_TAU,
+            d => d as f64 / 365.0,
+        };
+
+        let charm = charm(
+            option.mid_iv.unwrap_or(0.0),
+            tau,
+            current_price,
+            option.strike,
+            option.option_type == OptionType::Call,
+            options.risk_free_rate,
+            options.dividend_yield,
+        );
+        let exposure = if charm.is_finite() {
+            charm * option.open_interest as f64 * options.contract_multiplier
+        } else {
+            0.0
+        };
+
+        strike_to_charm_exposure
+            .entry(option.strike.to_string())
+            .or_default()
+            .add(option.option_type, exposure);
+    }
+
+    Ok(strike_to_charm_exposure)
+}
+
+pub fn charm_exposure(
+    symbol: &str,
+    option_chain: &[OptionInfo],
+    current_price: f64,
+    options: &GammaExposureOptions,
+) -> anyhow::Result<GammaExposureStats> {
+    let strike_to_charm_exposure = charm_exposure_by_price(option_chain, current_price, options)?;
+    let mut stats = GammaExposureStats::new(symbol, &strike_to_charm_exposure)?;
+    stats.spot_price = Some(current_price);
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::gamma_exposure::CONTRACT_MULTIPLIER;
+
+    fn option(strike: f64, option_type: OptionType, days: i64) -> OptionInfo {
+        let mut option = OptionInfo::test();
+        option.strike = strike;
+        option.option_type = option_type;
+        option.expiration_date = (Local::now().date_naive() + chrono::Duration::days(days))
+            .format("%Y-%m-%d")
+            .to_string();
+        option.mid_iv = Some(0.3);
+        option
+    }
+
+    #[test]
+    fn test_charm_exposure() {
+        let option_chain = vec![
+            option(110.0, OptionType::Call, 30),
+            option(110.0, OptionType::Put, 30),
+            option(105.0, OptionType::Call, 0),
+            option(95.0, OptionType::Call, -1),
+        ];
+        let exposure =
+            charm_exposure_by_price(&option_chain, 100.0, &GammaExposureOptions::default())
+                .unwrap();
+
+        let expected =
+            charm(0.3, 30.0 / 365.0, 100.0, 110.0, true, 0.0, 0.0) * 2.0 * CONTRACT_MULTIPLIER;
+        assert_eq!(expected, exposure["110"].call);
+        assert_eq!(-expected, exposure["110"].put);
+
+        let expected = charm(0.3, EXPIRATION_DAY_TAU, 100.0, 105.0, true, 0.0, 0.0)
+            * 2.0
+            * CONTRACT_MULTIPLIER;
+        assert_ne!(0.0, expected);
+        assert_eq!(expected, exposure["105"].net());
+
+        assert_eq!(0.0, exposure["95"].net());
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+
+use chrono::Local;
+
+use crate::{
+    math::bs::{call_delta, put_delta},
+    types::{GammaExposureStats, OptionInfo, OptionType},
+};
+
+use super::gamma_exposure::{parse_date, GammaExposureOptions, StrikeExposure};
+
+/// Delta * open interest * contract multiplier per strike. Put deltas are
+/// already negative, so unlike gamma they aren't flipped again.
+pub fn delta_exposure_by_price(
+    option_chain: &[OptionInfo],
+    current_price: f64,
+    options: &GammaExposureOptions,
+) -> anyhow::Result<BTreeMap<String, StrikeExposure>> {
+    let now = Local::now().date_naive();
+    let mut strike_to_delta_exposure: BTreeMap<String, StrikeExposure> = BTreeMap::new();
+
+    for option in option_chain.iter().filter(|o| options.includes(o)) {
+        let expiration_date = parse_date(&option.expiration_date)?;
+        let days_remaining = expiration_date.signed_duration_since(now).num_days();
+
+        let delta = match option.option_type {
+            OptionType::Call => call_delta,
+            OptionType::Put => put_delta,
+        };
+        let delta = delta(
+            option.mid_iv.unwrap_or(0.0),
+            days_remaining as f64 / 365.0,
+            current_price,
+            option.strike,
+            options.risk_free_rate,
+            options.dividend_yield,
+        );
+        let exposure = if delta.is_finite() {
+            delta * option.open_interest as f64 * options.contract_multiplier
+        } else {
+            0.0
+        };
+
+        let strike_exposure = strike_to_delta_exposure
+            .entry(option.strike.to_string())
+            .or_default();
+        match option.option_type {
+            OptionType::Call => strike_exposure.call += exposure,
+            OptionType::Put => strike_exposure.put += exposure,
+        }
+    }
+
+    Ok(strike_to_delta_exposure)
+}
+
+pub fn delta_exposure(
+    symbol: &str,
+    option_chain: &[OptionInfo],
+    current_price: f64,
+    options: &GammaExposureOptions,
+) -> anyhow::Result<GammaExposureStats> {
+    let strike_to_delta_exposure = delta_exposure_by_price(option_chain, current_price, options)?;
+    let mut stats = GammaExposureStats::new(symbol, &strike_to_delta_exposure)?;
+    stats.spot_price = Some(current_price);
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn option(strike: f64, option_type: OptionType) -> OptionInfo {
+        let mut option = OptionInfo::test();
+        option.strike = strike;
+        option.option_type = option_type;
+        option.expiration_date = (Local::now().date_naive() + chrono::Duration::days(30))
+            .format("%Y-%m-%d")
+            .to_string();
+        option.mid_iv = Some(0.3);
+        option
+    }
+
+    #[test]
+    fn test_put_delta_sign() {
+        let option_chain = vec![
+            option(100.0, OptionType::Call),
+            option(100.0, OptionType::Put),
+        ];
+        let exposure =
+            delta_exposure_by_price(&option_chain, 100.0, &GammaExposureOptions::default())
+                .unwrap();
+
+        let tau = 30.0 / 365.0;
+        assert!(exposure["100"].call > 0.0);
+        assert!(exposure["100"].put < 0.0);
+        assert_eq!(
+            call_delta(0.3, tau, 100.0, 100.0, 0.0, 0.0) * 2.0 * 100.0,
+            exposure["100"].call
+        );
+        assert_eq!(
+            put_delta(0.3, tau, 100.0, 100.0, 0.0, 0.0) * 2.0 * 100.0,
+            exposure["100"].put
+        );
+        // Call delta minus put delta is one per share.
+        assert!((exposure["100"].call - exposure["100"].put - 200.0).abs() < 1e-9);
+    }
+}
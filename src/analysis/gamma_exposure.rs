@@ -1,19 +1,149 @@
 use std::collections::BTreeMap;
 
-use chrono::{Date, FixedOffset, Local, TimeZone};
+use chrono::{Local, NaiveDate};
 
 use crate::{
-    math::bs::gamma,
+    math::{
+        binomial::american_gamma,
+        bs::{gamma, gamma_curve, DEFAULT_DIVIDEND_YIELD, DEFAULT_RISK_FREE_RATE},
+    },
     types::{
         gex::{GammaExposure, GammaExposureStats},
         OptionInfo, OptionType,
     },
 };
 
+const AMERICAN_TREE_STEPS: usize = 50;
+
+pub const DEFAULT_MAX_GAMMA: f64 = 1.0;
+pub const CONTRACT_MULTIPLIER: f64 = 100.0;
+// Spacing of the hypothetical spot prices the aggregate is evaluated at.
+const AGGREGATE_PRICE_STEP: f64 = 0.5;
+
+#[derive(Clone, Debug)]
+pub struct GammaExposureOptions {
+    pub max_gamma: f64,
+    pub risk_free_rate: f64,
+    pub dividend_yield: f64,
+    pub american: bool,
+    pub spot: Option<f64>,
+    pub dollar: bool,
+    pub expiration: Option<String>,
+    pub min_dte: Option<i64>,
+    pub max_dte: Option<i64>,
+    pub contract_multiplier: f64,
+    /// Fail instead of skipping when more than this fraction of options has
+    /// no greeks.
+    pub max_missing_greeks: Option<f64>,
+    /// Evaluate the aggregate within this distance of `spot` instead of
+    /// across every strike.
+    pub spot_band: Option<f64>,
+    /// Only count calls or only puts, e.g. to find put walls.
+    pub option_type: Option<OptionType>,
+    /// Divide each strike's exposure by the local strike spacing, so
+    /// profiles of symbols with $1 and $5 strikes can be compared.
+    pub normalize_strike_spacing: bool,
+    /// Drop strikes whose combined open interest is below this.
+    pub min_open_interest: u64,
+}
+
+impl GammaExposureOptions {
+    // Shares per contract, and dollar gamma for a 1% move in the underlying
+    // when requested.
+    fn exposure_scale(&self) -> f64 {
+        match self.spot {
+            Some(spot) if self.dollar => spot * spot * 0.01 * self.contract_multiplier,
+            _ => self.contract_multiplier,
+        }
+    }
+
+    pub(crate) fn includes(&self, option: &OptionInfo) -> bool {
+        self.expiration
+            .as_ref()
+            .is_none_or(|expiration| *expiration == option.expiration_date)
+            && self
+                .option_type
+                .is_none_or(|option_type| option_type == option.option_type)
+    }
+
+    /// The first and last aggregate grid prices within `spot_band` of `spot`,
+    /// counted in grid steps. Spots that land on the same grid share it.
+    pub(crate) fn band_steps(&self) -> Option<(i64, i64)> {
+        let (spot, band) = (self.spot?, self.spot_band?);
+        Some((
+            ((spot - band) / AGGREGATE_PRICE_STEP).floor() as i64,
+            ((spot + band) / AGGREGATE_PRICE_STEP).floor() as i64,
+        ))
+    }
+
+    fn includes_dte(&self, days_remaining: i64) -> bool {
+        self.min_dte.is_none_or(|min| days_remaining >= min)
+            && self.max_dte.is_none_or(|max| days_remaining <= max)
+    }
+}
+
+/// Gamma exposure at a single strike, with puts carrying a negative sign.
+/// Open interest and volume are summed over calls and puts, and left at zero
+/// where exposures aren't built from strikes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StrikeExposure {
+    pub call: f64,
+    pub put: f64,
+    pub open_interest: u64,
+    pub volume: u64,
+}
+
+impl StrikeExposure {
+    pub fn net(&self) -> f64 {
+        self.call + self.put
+    }
+
+    pub(crate) fn add(&mut self, option_type: OptionType, exposure: f64) {
+        match option_type {
+            OptionType::Call => self.call += exposure,
+            OptionType::Put => self.put -= exposure,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GreeksCoverage {
+    pub included: usize,
+    pub missing: usize,
+}
+
+impl GreeksCoverage {
+    pub fn missing_fraction(&self) -> f64 {
+        divide_or_zero(self.missing as f64, (self.included + self.missing) as f64)
+    }
+}
+
+impl Default for GammaExposureOptions {
+    fn default() -> Self {
+        Self {
+            max_gamma: DEFAULT_MAX_GAMMA,
+            risk_free_rate: DEFAULT_RISK_FREE_RATE,
+            dividend_yield: DEFAULT_DIVIDEND_YIELD,
+            american: false,
+            spot: None,
+            dollar: false,
+            expiration: None,
+            min_dte: None,
+            max_dte: None,
+            contract_multiplier: CONTRACT_MULTIPLIER,
+            max_missing_greeks: None,
+            spot_band: None,
+            option_type: None,
+            normalize_strike_spacing: false,
+            min_open_interest: 0,
+        }
+    }
+}
+
 impl GammaExposureStats {
     pub fn new(
         symbol: impl Into<String>,
-        strike_to_gamma_exposure: &BTreeMap<String, f64>,
+        strike_to_gamma_exposure: &BTreeMap<String, StrikeExposure>,
     ) -> anyhow::Result<Self> {
         let mut positive_sum: f64 = 0.0;
         let mut positive_count = 0;
@@ -25,22 +155,25 @@ impl GammaExposureStats {
         let mut absolute_minimum: f64 = f64::MAX;
         let mut weighted_positive_sum: f64 = 0.0;
         let mut weighted_negative_sum: f64 = 0.0;
+        let mut weighted_absolute_sum: f64 = 0.0;
         let mut absolute_maximum_price: f64 = 0.0;
         let mut absolute_minimum_price: f64 = f64::MAX;
 
         for (strike, exposure) in strike_to_gamma_exposure {
             let strike: f64 = strike.parse()?;
-            if *exposure >= 0.0 {
+            let exposure = exposure.net();
+            if exposure >= 0.0 {
                 positive_sum += exposure;
                 weighted_positive_sum += strike * exposure;
                 positive_count += 1;
             } else {
                 negative_sum += exposure;
-                weighted_negative_sum += strike * *exposure;
+                weighted_negative_sum += strike * exposure;
                 negative_count += 1;
             }
-            maximum_gamma_exposure = maximum_gamma_exposure.max(*exposure);
-            minimum_gamma_exposure = minimum_gamma_exposure.min(*exposure);
+            weighted_absolute_sum += strike * exposure.abs();
+            maximum_gamma_exposure = maximum_gamma_exposure.max(exposure);
+            minimum_gamma_exposure = minimum_gamma_exposure.min(exposure);
 
             if exposure.abs() >= absolute_maximum {
                 absolute_maximum = exposure.abs();
@@ -53,6 +186,13 @@ impl GammaExposureStats {
             }
         }
 
+        if strike_to_gamma_exposure.is_empty() {
+            absolute_minimum = 0.0;
+            absolute_minimum_price = 0.0;
+        }
+
+        let total_exposure = positive_sum + negative_sum;
+
         positive_count = positive_count.max(1);
         negative_count = negative_count.max(1);
 
@@ -61,23 +201,34 @@ impl GammaExposureStats {
         let average_absolute_exposure =
             (positive_sum.abs() + negative_sum.abs()) / (positive_count + negative_count) as f64;
 
-        let weighted_average_absolute_price = (weighted_positive_sum.abs()
-            + weighted_negative_sum.abs())
-            / (positive_sum.abs() + negative_sum.abs());
-        let weighted_average_positive_price = weighted_positive_sum / positive_sum;
-        let weighted_average_negative_price = weighted_negative_sum / negative_sum;
+        // An empty or all-zero profile has no weight to average over.
+        let weighted_average_absolute_price = divide_or_zero(
+            weighted_positive_sum.abs() + weighted_negative_sum.abs(),
+            positive_sum.abs() + negative_sum.abs(),
+        );
+        let weighted_average_positive_price = divide_or_zero(weighted_positive_sum, positive_sum);
+        let weighted_average_negative_price = divide_or_zero(weighted_negative_sum, negative_sum);
 
-        let mut prices: Vec<GammaExposure> = strike_to_gamma_exposure
+        let absolute_sum = positive_sum.abs() + negative_sum.abs();
+        let gamma_center = divide_or_zero(weighted_absolute_sum, absolute_sum);
+        let mut prices: Vec<(f64, GammaExposure)> = strike_to_gamma_exposure
             .iter()
-            .map(|(strike, exposure)| GammaExposure::new(strike.clone(), *exposure))
-            .collect();
+            .map(|(strike, exposure)| {
+                let mut price = GammaExposure::new(strike.clone(), exposure);
+                price.percent_of_total = divide_or_zero(exposure.net().abs(), absolute_sum);
+                Ok((strike.parse()?, price))
+            })
+            .collect::<anyhow::Result<_>>()?;
 
-        prices.sort_by(|p1, p2| p1.strike.cmp(&p2.strike));
+        prices.sort_by(|(s1, _), (s2, _)| s1.total_cmp(s2));
+        let prices = prices.into_iter().map(|(_, price)| price).collect();
 
-        Ok(Self {
-            timestamp: chrono::Utc::now().to_rfc3339(),
+        let computed_at = chrono::Utc::now().to_rfc3339();
+        let mut stats = Self {
+            timestamp: computed_at.clone(),
             symbol: symbol.into(),
             prices,
+            total_exposure,
             average_absolute_exposure,
             average_positive_exposure,
             average_negative_exposure,
@@ -90,109 +241,386 @@ impl GammaExposureStats {
             weighted_average_negative_price,
             absolute_maximum_price,
             absolute_minimum_price,
-        })
+            gamma_center,
+            call_wall: 0.0,
+            put_wall: 0.0,
+            gamma_flip_point: None,
+            spot_price: None,
+            computed_at,
+            chain_timestamp: None,
+        };
+        (stats.call_wall, stats.put_wall) = gamma_walls(&stats);
+        stats.gamma_flip_point = gamma_flip_point(&stats);
+
+        Ok(stats)
+    }
+}
+
+/// The price where cumulative net gamma exposure, summed up from the lowest
+/// strike, crosses zero. Interpolates linearly between the adjacent strikes.
+pub fn gamma_flip_point(stats: &GammaExposureStats) -> Option<f64> {
+    let mut previous: Option<(f64, f64)> = None;
+    let mut cumulative = 0.0;
+
+    for price in &stats.prices {
+        let strike: f64 = price.strike.parse().ok()?;
+        cumulative += price.gamma_exposure;
+
+        if let Some((previous_strike, previous_cumulative)) = previous {
+            // Landing exactly on zero is a crossing at the strike itself.
+            if previous_cumulative != 0.0 && cumulative == 0.0 {
+                return Some(strike);
+            }
+            if previous_cumulative * cumulative < 0.0 {
+                let fraction = previous_cumulative / (previous_cumulative - cumulative);
+                return Some(previous_strike + fraction * (strike - previous_strike));
+            }
+        }
+        previous = Some((strike, cumulative));
+    }
+
+    None
+}
+
+/// Strikes of the largest positive (call wall) and most negative (put wall)
+/// net exposure. Either is zero when no strike has exposure of that sign.
+pub fn gamma_walls(stats: &GammaExposureStats) -> (f64, f64) {
+    let mut call_wall = (0.0, 0.0);
+    let mut put_wall = (0.0, 0.0);
+
+    for price in &stats.prices {
+        let strike: f64 = match price.strike.parse() {
+            Ok(strike) => strike,
+            Err(_) => continue,
+        };
+        if price.gamma_exposure > call_wall.1 {
+            call_wall = (strike, price.gamma_exposure);
+        }
+        if price.gamma_exposure < put_wall.1 {
+            put_wall = (strike, price.gamma_exposure);
+        }
+    }
+
+    (call_wall.0, put_wall.0)
+}
+
+fn divide_or_zero(numerator: f64, denominator: f64) -> f64 {
+    if denominator == 0.0 {
+        0.0
+    } else {
+        numerator / denominator
     }
 }
 
 impl GammaExposure {
-    pub fn new(strike: String, gamma_exposure: f64) -> Self {
+    pub fn new(strike: String, exposure: &StrikeExposure) -> Self {
         Self {
             strike,
-            gamma_exposure,
+            gamma_exposure: exposure.net(),
+            call_exposure: exposure.call,
+            put_exposure: exposure.put,
+            percent_of_total: 0.0,
+            open_interest: exposure.open_interest,
+            volume: exposure.volume,
         }
     }
 }
 
-pub fn gamma_exposure_by_price(option_chain: &[OptionInfo]) -> BTreeMap<String, f64> {
-    let mut strike_to_gamma_exposure: BTreeMap<String, f64> = BTreeMap::new();
+pub fn gamma_exposure_by_price(
+    option_chain: &[OptionInfo],
+    options: &GammaExposureOptions,
+) -> BTreeMap<String, StrikeExposure> {
+    let mut strike_to_gamma_exposure: BTreeMap<String, StrikeExposure> = BTreeMap::new();
+    let max_gamma = options.max_gamma;
+    let scale = options.exposure_scale();
 
-    for option in option_chain {
-        let strike = option.strike.to_string();
-        let mut exposure = if !(-1.0..=1.0).contains(&option.gamma()) {
+    for option in option_chain.iter().filter(|o| options.includes(o)) {
+        let gamma = match &option.greeks {
+            Some(greeks) => greeks.gamma,
+            None => continue,
+        };
+        let exposure = if !(-max_gamma..=max_gamma).contains(&gamma) {
             0.0
         } else {
-            option.gamma() * option.open_interest as f64
+            gamma * option.open_interest as f64 * scale
         };
-        if option.option_type == OptionType::Put {
-            exposure *= -1.0;
-        }
-        match strike_to_gamma_exposure.get_mut(&strike) {
-            Some(exp) => *exp += exposure,
-            None => {
-                strike_to_gamma_exposure.insert(strike, exposure);
-            }
-        }
+        let strike_exposure = strike_to_gamma_exposure
+            .entry(option.strike.to_string())
+            .or_default();
+        strike_exposure.add(option.option_type, exposure);
+        strike_exposure.open_interest += option.open_interest;
+        strike_exposure.volume += option.volume;
+    }
+
+    let coverage = greeks_coverage(option_chain, options);
+    if coverage.missing > 0 {
+        log::warn!(
+            "Skipped {} of {} options with missing greeks",
+            coverage.missing,
+            coverage.included + coverage.missing
+        );
+    }
+
+    // Spacing comes from the full strike grid, so dropping thin strikes
+    // doesn't widen their neighbours.
+    if options.normalize_strike_spacing {
+        normalize_strike_spacing(&mut strike_to_gamma_exposure);
     }
 
+    strike_to_gamma_exposure
+        .retain(|_, exposure| exposure.open_interest >= options.min_open_interest);
+
     strike_to_gamma_exposure
 }
 
+// Turns each strike's exposure into exposure per dollar of strike width. The
+// width is half the distance between the neighbouring strikes, or the
+// distance to the only neighbour at either end. A lone strike is left as is.
+fn normalize_strike_spacing(strike_to_exposure: &mut BTreeMap<String, StrikeExposure>) {
+    let mut strikes: Vec<(f64, String)> = strike_to_exposure
+        .keys()
+        .filter_map(|strike| Some((strike.parse().ok()?, strike.clone())))
+        .collect();
+    strikes.sort_by(|(s1, _), (s2, _)| s1.total_cmp(s2));
+
+    if strikes.len() < 2 {
+        return;
+    }
+
+    for i in 0..strikes.len() {
+        let below = strikes[i.saturating_sub(1)].0;
+        let above = strikes[(i + 1).min(strikes.len() - 1)].0;
+        let width = if i == 0 || i == strikes.len() - 1 {
+            above - below
+        } else {
+            (above - below) / 2.0
+        };
+
+        if let Some(exposure) = strike_to_exposure.get_mut(&strikes[i].1) {
+            exposure.call = divide_or_zero(exposure.call, width);
+            exposure.put = divide_or_zero(exposure.put, width);
+        }
+    }
+}
+
+pub fn greeks_coverage(
+    option_chain: &[OptionInfo],
+    options: &GammaExposureOptions,
+) -> GreeksCoverage {
+    let mut coverage = GreeksCoverage::default();
+    for option in option_chain.iter().filter(|o| options.includes(o)) {
+        if option.greeks.is_some() {
+            coverage.included += 1;
+        } else {
+            coverage.missing += 1;
+        }
+    }
+    coverage
+}
+
 pub fn gamma_exposure(
     symbol: &str,
     option_chain: &[OptionInfo],
+    options: &GammaExposureOptions,
 ) -> anyhow::Result<GammaExposureStats> {
-    let strike_to_gamma_exposure = gamma_exposure_by_price(option_chain);
-    GammaExposureStats::new(symbol, &strike_to_gamma_exposure)
+    if let Some(max_missing_greeks) = options.max_missing_greeks {
+        let coverage = greeks_coverage(option_chain, options);
+        if coverage.missing_fraction() > max_missing_greeks {
+            anyhow::bail!(
+                "{} of {} options for {} are missing greeks",
+                coverage.missing,
+                coverage.included + coverage.missing,
+                symbol
+            );
+        }
+    }
+
+    let strike_to_gamma_exposure = gamma_exposure_by_price(option_chain, options);
+    let mut stats = GammaExposureStats::new(symbol, &strike_to_gamma_exposure)?;
+    stats.spot_price = options.spot;
+    Ok(stats)
 }
 
 pub fn gamma_exposure_aggregate(
     symbol: &str,
     option_chain: &[OptionInfo],
+    options: &GammaExposureOptions,
 ) -> anyhow::Result<GammaExposureStats> {
-    let now = Local::now().date();
-    let mut strike_to_gamma_exposure_aggregate: BTreeMap<String, f64> = BTreeMap::new();
+    let now = Local::now().date_naive();
+    let max_gamma = options.max_gamma;
+    let mut strike_to_gamma_exposure_aggregate: BTreeMap<String, StrikeExposure> = BTreeMap::new();
 
-    let min_price = option_chain
-        .iter()
-        .map(|o| o.strike)
-        .min_by(|s1, s2| s1.partial_cmp(s2).unwrap_or(std::cmp::Ordering::Less))
-        .unwrap_or(0.0);
+    let prices = match options.band_steps() {
+        // Snapped to the step so banded keys line up with the unbanded grid.
+        Some((lo, hi)) => price_grid(
+            lo as f64 * AGGREGATE_PRICE_STEP,
+            hi as f64 * AGGREGATE_PRICE_STEP,
+            AGGREGATE_PRICE_STEP,
+        ),
+        None => {
+            let min_price = option_chain
+                .iter()
+                .map(|o| o.strike)
+                .min_by(|s1, s2| s1.partial_cmp(s2).unwrap_or(std::cmp::Ordering::Less))
+                .unwrap_or(0.0);
 
-    let max_price = option_chain
-        .iter()
-        .map(|o| o.strike)
-        .max_by(|s1, s2| s1.partial_cmp(s2).unwrap_or(std::cmp::Ordering::Less))
-        .unwrap_or(0.0);
+            let max_price = option_chain
+                .iter()
+                .map(|o| o.strike)
+                .max_by(|s1, s2| s1.partial_cmp(s2).unwrap_or(std::cmp::Ordering::Less))
+                .unwrap_or(0.0);
 
-    let price_offset = 0.5;
+            price_grid(min_price.floor(), max_price, AGGREGATE_PRICE_STEP)
+        }
+    };
 
-    for option in option_chain {
+    for option in option_chain.iter().filter(|o| options.includes(o)) {
         let expiration_date = parse_date(&option.expiration_date)?;
         let days_remaining = expiration_date.signed_duration_since(now).num_days();
+        if !options.includes_dte(days_remaining) {
+            continue;
+        }
 
         let sigma = option.mid_iv.unwrap_or(0.0);
-        let expiration_time = days_remaining as f64 / 365.0;
-        let current_time = 0.0;
+        let tau = days_remaining as f64 / 365.0;
         let strike = option.strike;
 
-        let mut price = min_price.floor();
-        while price <= max_price {
+        let gammas = if options.american {
+            let is_call = option.option_type == OptionType::Call;
+            prices
+                .iter()
+                .map(|price| {
+                    american_gamma(
+                        sigma,
+                        tau,
+                        *price,
+                        strike,
+                        is_call,
+                        options.risk_free_rate,
+                        options.dividend_yield,
+                        AMERICAN_TREE_STEPS,
+                    )
+                })
+                .collect()
+        } else {
+            gamma_curve(
+                sigma,
+                tau,
+                &prices,
+                strike,
+                options.risk_free_rate,
+                options.dividend_yield,
+            )
+        };
+
+        for (price, gamma) in prices.iter().zip(gammas) {
             let price_string = price.to_string();
-            let gamma = gamma(sigma, expiration_time, current_time, price, strike);
 
-            let mut exposure = if !(-1.0..=1.0).contains(&gamma) || gamma.is_nan() {
+            let exposure = if !(-max_gamma..=max_gamma).contains(&gamma) || gamma.is_nan() {
                 0.0
             } else {
-                gamma * option.open_interest as f64
+                gamma * option.open_interest as f64 * options.contract_multiplier
             };
-            if option.option_type == OptionType::Put {
-                exposure *= -1.0;
-            }
-            match strike_to_gamma_exposure_aggregate.get_mut(&price_string) {
-                Some(exp) => *exp += exposure,
-                None => {
-                    strike_to_gamma_exposure_aggregate.insert(price_string.clone(), exposure);
-                }
-            }
+            strike_to_gamma_exposure_aggregate
+                .entry(price_string)
+                .or_default()
+                .add(option.option_type, exposure);
+        }
+    }
+
+    let mut stats = GammaExposureStats::new(symbol, &strike_to_gamma_exposure_aggregate)?;
+    stats.spot_price = options.spot;
+    Ok(stats)
+}
 
-            price += price_offset;
+/// Sums per-strike exposure into `[lo, lo + bucket_width)` bins, returned as
+/// (lo, exposure) in ascending order.
+pub fn gamma_histogram(stats: &GammaExposureStats, bucket_width: f64) -> Vec<(f64, f64)> {
+    if bucket_width <= 0.0 {
+        return Vec::new();
+    }
+
+    let mut buckets: BTreeMap<i64, f64> = BTreeMap::new();
+    for price in &stats.prices {
+        if let Ok(strike) = price.strike.parse::<f64>() {
+            let bucket = (strike / bucket_width).floor() as i64;
+            *buckets.entry(bucket).or_default() += price.gamma_exposure;
         }
     }
 
-    GammaExposureStats::new(symbol, &strike_to_gamma_exposure_aggregate)
+    buckets
+        .into_iter()
+        .map(|(bucket, exposure)| (bucket as f64 * bucket_width, exposure))
+        .collect()
 }
 
-fn parse_date(date: &str) -> anyhow::Result<Date<FixedOffset>> {
+/// Net gamma exposure of the chain evaluated at each hypothetical spot price
+/// from `lo` to `hi` in increments of `step`.
+pub fn gamma_profile(
+    option_chain: &[OptionInfo],
+    lo: f64,
+    hi: f64,
+    step: f64,
+    options: &GammaExposureOptions,
+) -> anyhow::Result<Vec<(f64, f64)>> {
+    if step <= 0.0 || hi < lo {
+        return Ok(Vec::new());
+    }
+
+    let now = Local::now().date_naive();
+    let mut taus = Vec::new();
+    for option in option_chain.iter().filter(|o| options.includes(o)) {
+        let expiration_date = parse_date(&option.expiration_date)?;
+        let days_remaining = expiration_date.signed_duration_since(now).num_days();
+        if options.includes_dte(days_remaining) {
+            taus.push((option, days_remaining as f64 / 365.0));
+        }
+    }
+
+    let steps = ((hi - lo) / step).floor() as usize;
+    let profile = (0..=steps)
+        .map(|i| {
+            let price = lo + i as f64 * step;
+            let exposure = taus
+                .iter()
+                .map(|(option, tau)| {
+                    let gamma = gamma(
+                        option.mid_iv.unwrap_or(0.0),
+                        *tau,
+                        price,
+                        option.strike,
+                        options.risk_free_rate,
+                        options.dividend_yield,
+                    );
+                    if !(-options.max_gamma..=options.max_gamma).contains(&gamma) {
+                        return 0.0;
+                    }
+                    let exposure =
+                        gamma * option.open_interest as f64 * options.contract_multiplier;
+                    match option.option_type {
+                        OptionType::Call => exposure,
+                        OptionType::Put => -exposure,
+                    }
+                })
+                .sum();
+            (price, exposure)
+        })
+        .collect();
+
+    Ok(profile)
+}
+
+fn price_grid(lo: f64, hi: f64, step: f64) -> Vec<f64> {
+    let mut prices = Vec::new();
+    let mut price = lo;
+    while price <= hi {
+        prices.push(price);
+        price += step;
+    }
+    prices
+}
+
+pub(crate) fn parse_date(date: &str) -> anyhow::Result<NaiveDate> {
     let mut split_date = date.split('-');
 
     let y = split_date
@@ -208,6 +636,599 @@ fn parse_date(date: &str) -> anyhow::Result<Date<FixedOffset>> {
         .ok_or_else(|| anyhow::anyhow!("Invalid day"))?
         .parse()?;
 
-    let ny_offset = FixedOffset::east(5 * 3600);
-    Ok(ny_offset.ymd(y, m, d))
+    NaiveDate::from_ymd_opt(y, m, d).ok_or_else(|| anyhow::anyhow!("Invalid date: {}", date))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Greeks;
+
+    fn option_with_gamma(strike: f64, gamma: f64) -> OptionInfo {
+        let mut option = OptionInfo::test();
+        option.strike = strike;
+        option.greeks = Some(Greeks {
+            gamma,
+            ..option.greeks.unwrap()
+        });
+        option
+    }
+
+    fn profile(exposures: &[(&str, f64)]) -> BTreeMap<String, StrikeExposure> {
+        exposures
+            .iter()
+            .map(|(strike, exposure)| {
+                let exposure = StrikeExposure {
+                    call: exposure.max(0.0),
+                    put: exposure.min(0.0),
+                    ..Default::default()
+                };
+                (strike.to_string(), exposure)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_prices_sorted_numerically() {
+        let strike_to_gamma_exposure =
+            profile(&[("9", 1.0), ("10", 1.0), ("100", 1.0), ("95", 1.0)]);
+
+        let stats = GammaExposureStats::new("TST", &strike_to_gamma_exposure).unwrap();
+        let strikes: Vec<&str> = stats.prices.iter().map(|p| p.strike.as_str()).collect();
+        assert_eq!(vec!["9", "10", "95", "100"], strikes);
+    }
+
+    #[test]
+    fn test_empty_chain_has_no_nan() {
+        let stats = GammaExposureStats::new("TST", &BTreeMap::new()).unwrap();
+        assert!(stats.prices.is_empty());
+        for value in &[
+            stats.average_absolute_exposure,
+            stats.average_positive_exposure,
+            stats.average_negative_exposure,
+            stats.maximum_gamma_exposure,
+            stats.minimum_gamma_exposure,
+            stats.absolute_maximum,
+            stats.absolute_minimum,
+            stats.weighted_average_absolute_price,
+            stats.weighted_average_positive_price,
+            stats.weighted_average_negative_price,
+            stats.absolute_maximum_price,
+            stats.absolute_minimum_price,
+            stats.gamma_center,
+        ] {
+            assert_eq!(0.0, *value);
+        }
+
+        let stats = GammaExposureStats::new("TST", &profile(&[("10", 0.0)])).unwrap();
+        assert_eq!(0.0, stats.weighted_average_absolute_price);
+        assert_eq!(0.0, stats.weighted_average_positive_price);
+        assert_eq!(0.0, stats.weighted_average_negative_price);
+    }
+
+    #[test]
+    fn test_one_sided_profile_has_no_nan() {
+        let stats =
+            GammaExposureStats::new("TST", &profile(&[("90", -2.0), ("100", -1.0)])).unwrap();
+        assert_eq!(0.0, stats.weighted_average_positive_price);
+        assert_eq!(0.0, stats.average_positive_exposure);
+        assert!((stats.weighted_average_negative_price - 280.0 / 3.0).abs() < 1e-12);
+
+        // serde_json writes NaN and infinity as null, which GraphQL rejects
+        // for non-null floats.
+        let json = serde_json::to_value(&stats).unwrap();
+        for (field, value) in json.as_object().unwrap() {
+            let optional =
+                ["gamma_flip_point", "spot_price", "chain_timestamp"].contains(&field.as_str());
+            assert!(optional || !value.is_null(), "{} is not finite", field);
+        }
+    }
+
+    #[test]
+    fn test_total_exposure() {
+        let stats = GammaExposureStats::new(
+            "TST",
+            &profile(&[("90", -2.0), ("100", -1.0), ("110", 4.5)]),
+        )
+        .unwrap();
+        assert_eq!(1.5, stats.total_exposure);
+
+        let positive_sum = stats.average_positive_exposure;
+        let negative_sum = stats.average_negative_exposure * 2.0;
+        assert_eq!(positive_sum + negative_sum, stats.total_exposure);
+    }
+
+    #[test]
+    fn test_percent_of_total() {
+        let stats =
+            GammaExposureStats::new("TST", &profile(&[("90", -2.0), ("100", 1.0), ("110", 4.5)]))
+                .unwrap();
+        let total: f64 = stats.prices.iter().map(|p| p.percent_of_total).sum();
+        assert!((total - 1.0).abs() < 1e-12);
+        assert!((stats.prices[0].percent_of_total - 2.0 / 7.5).abs() < 1e-12);
+
+        let stats = GammaExposureStats::new("TST", &profile(&[("10", 0.0)])).unwrap();
+        assert_eq!(0.0, stats.prices[0].percent_of_total);
+    }
+
+    #[test]
+    fn test_gamma_center() {
+        // (100 * 1 + 110 * 3) / (1 + 3)
+        let stats =
+            GammaExposureStats::new("TST", &profile(&[("100", 1.0), ("110", -3.0)])).unwrap();
+        assert_eq!(107.5, stats.gamma_center);
+
+        let stats =
+            GammaExposureStats::new("TST", &profile(&[("100", 0.0), ("110", 0.0)])).unwrap();
+        assert_eq!(0.0, stats.gamma_center);
+    }
+
+    #[test]
+    fn test_gamma_walls() {
+        let strike_to_gamma_exposure = profile(&[
+            ("90", -3.0),
+            ("95", -5.0),
+            ("100", 1.0),
+            ("105", 6.0),
+            ("110", 2.0),
+        ]);
+
+        let stats = GammaExposureStats::new("TST", &strike_to_gamma_exposure).unwrap();
+        assert_eq!((105.0, 95.0), gamma_walls(&stats));
+        assert_eq!(105.0, stats.call_wall);
+        assert_eq!(95.0, stats.put_wall);
+        assert_eq!(6.0, stats.maximum_gamma_exposure);
+
+        let stats = GammaExposureStats::new("TST", &profile(&[("100", 1.0)])).unwrap();
+        assert_eq!((100.0, 0.0), gamma_walls(&stats));
+    }
+
+    #[test]
+    fn test_gamma_histogram() {
+        let stats = GammaExposureStats::new(
+            "TST",
+            &profile(&[("100", 1.0), ("101", -2.0), ("102", 4.0), ("105", 8.0)]),
+        )
+        .unwrap();
+        assert_eq!(
+            vec![(100.0, 3.0), (105.0, 8.0)],
+            gamma_histogram(&stats, 5.0)
+        );
+        assert!(gamma_histogram(&stats, 0.0).is_empty());
+    }
+
+    #[test]
+    fn test_gamma_flip_point() {
+        let stats = GammaExposureStats::new(
+            "TST",
+            &profile(&[("90", -2.0), ("100", -1.0), ("110", 4.0)]),
+        )
+        .unwrap();
+        assert_eq!(Some(107.5), stats.gamma_flip_point);
+        assert_eq!(Some(107.5), gamma_flip_point(&stats));
+
+        let stats = GammaExposureStats::new("TST", &profile(&[("90", 1.0), ("100", 2.0)])).unwrap();
+        assert_eq!(None, stats.gamma_flip_point);
+
+        let stats =
+            GammaExposureStats::new("TST", &profile(&[("90", -2.0), ("100", 2.0), ("110", 3.0)]))
+                .unwrap();
+        assert_eq!(Some(100.0), stats.gamma_flip_point);
+    }
+
+    #[test]
+    fn test_dollar_exposure() {
+        let option_chain = vec![option_with_gamma(10.0, 0.5)];
+        let options = GammaExposureOptions {
+            spot: Some(50.0),
+            dollar: true,
+            ..Default::default()
+        };
+
+        let exposure = gamma_exposure_by_price(&option_chain, &options);
+        assert_eq!(0.5 * 2.0 * 50.0 * 50.0 * 0.01 * 100.0, exposure["10"].net());
+
+        let options = GammaExposureOptions {
+            dollar: false,
+            ..options
+        };
+        let exposure = gamma_exposure_by_price(&option_chain, &options);
+        assert_eq!(100.0, exposure["10"].net());
+    }
+
+    #[test]
+    fn test_expiration_filter() {
+        let mut near = option_with_gamma(10.0, 0.5);
+        near.expiration_date = "2021-01-15".to_string();
+        let mut far = option_with_gamma(10.0, 0.25);
+        far.expiration_date = "2021-02-19".to_string();
+        let option_chain = vec![near, far];
+
+        let exposure = gamma_exposure_by_price(&option_chain, &GammaExposureOptions::default());
+        assert_eq!(150.0, exposure["10"].net());
+
+        let options = GammaExposureOptions {
+            expiration: Some("2021-02-19".to_string()),
+            ..Default::default()
+        };
+        let exposure = gamma_exposure_by_price(&option_chain, &options);
+        assert_eq!(50.0, exposure["10"].net());
+    }
+
+    #[test]
+    fn test_dte_filter() {
+        let option_expiring_in = |days: i64| {
+            let mut option = option_with_gamma(100.0, 0.0);
+            option.expiration_date = (Local::now().date_naive() + chrono::Duration::days(days))
+                .format("%Y-%m-%d")
+                .to_string();
+            option.mid_iv = Some(0.3);
+            option.open_interest = days as u64;
+            option
+        };
+        let option_chain: Vec<OptionInfo> =
+            [1, 10, 45].iter().map(|d| option_expiring_in(*d)).collect();
+        let exposures = |chain: &[OptionInfo], min_dte, max_dte| -> Vec<f64> {
+            let options = GammaExposureOptions {
+                min_dte,
+                max_dte,
+                ..Default::default()
+            };
+            gamma_exposure_aggregate("TST", chain, &options)
+                .unwrap()
+                .prices
+                .iter()
+                .map(|p| p.gamma_exposure)
+                .collect()
+        };
+
+        assert_eq!(
+            exposures(&option_chain[..1], None, None),
+            exposures(&option_chain, None, Some(7))
+        );
+        assert_eq!(
+            exposures(&option_chain[1..2], None, None),
+            exposures(&option_chain, Some(5), Some(30))
+        );
+        assert_eq!(
+            exposures(&option_chain[2..], None, None),
+            exposures(&option_chain, Some(30), None)
+        );
+        assert_ne!(
+            exposures(&option_chain, None, None),
+            exposures(&option_chain, Some(30), None)
+        );
+    }
+
+    #[test]
+    fn test_gamma_profile() {
+        let mut option = option_with_gamma(100.0, 0.0);
+        option.expiration_date = (Local::now().date_naive() + chrono::Duration::days(30))
+            .format("%Y-%m-%d")
+            .to_string();
+        option.mid_iv = Some(0.3);
+
+        let profile = gamma_profile(
+            &[option],
+            80.0,
+            120.0,
+            1.0,
+            &GammaExposureOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(41, profile.len());
+        assert_eq!((80.0, 120.0), (profile[0].0, profile[40].0));
+
+        for window in profile.windows(2) {
+            let ((p1, e1), (_, e2)) = (window[0], window[1]);
+            assert!(e1 > 0.0 && e2 > 0.0);
+            if p1 < 95.0 {
+                assert!(e2 > e1);
+            } else if p1 >= 100.0 {
+                assert!(e2 < e1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_spot_band() {
+        let option_chain: Vec<OptionInfo> = [50.0, 100.0, 150.0]
+            .iter()
+            .map(|strike| {
+                let mut option = option_with_gamma(*strike, 0.0);
+                option.expiration_date = (Local::now().date_naive() + chrono::Duration::days(30))
+                    .format("%Y-%m-%d")
+                    .to_string();
+                option.mid_iv = Some(0.3);
+                option
+            })
+            .collect();
+
+        let stats =
+            gamma_exposure_aggregate("TST", &option_chain, &GammaExposureOptions::default())
+                .unwrap();
+        assert_eq!(201, stats.prices.len());
+
+        let options = GammaExposureOptions {
+            spot: Some(101.0),
+            spot_band: Some(2.0),
+            ..Default::default()
+        };
+        let stats = gamma_exposure_aggregate("TST", &option_chain, &options).unwrap();
+        let strikes: Vec<f64> = stats
+            .prices
+            .iter()
+            .map(|p| p.strike.parse().unwrap())
+            .collect();
+        assert_eq!(
+            vec![99.0, 99.5, 100.0, 100.5, 101.0, 101.5, 102.0, 102.5, 103.0],
+            strikes
+        );
+
+        let options = GammaExposureOptions {
+            spot: Some(101.73),
+            spot_band: Some(2.0),
+            ..Default::default()
+        };
+        let stats = gamma_exposure_aggregate("TST", &option_chain, &options).unwrap();
+        let strikes: Vec<&str> = stats.prices.iter().map(|p| p.strike.as_str()).collect();
+        assert_eq!(
+            vec!["99.5", "100", "100.5", "101", "101.5", "102", "102.5", "103", "103.5"],
+            strikes
+        );
+        for strike in strikes {
+            let strike: f64 = strike.parse().unwrap();
+            assert_eq!(0.0, strike % 0.5, "{}", strike);
+        }
+    }
+
+    #[test]
+    fn test_max_gamma() {
+        let option_chain = vec![option_with_gamma(10.0, 0.5), option_with_gamma(11.0, 1.5)];
+
+        let exposure = gamma_exposure_by_price(&option_chain, &GammaExposureOptions::default());
+        assert_eq!(100.0, exposure["10"].net());
+        assert_eq!(0.0, exposure["11"].net());
+
+        let options = GammaExposureOptions {
+            max_gamma: 2.0,
+            ..Default::default()
+        };
+        let exposure = gamma_exposure_by_price(&option_chain, &options);
+        assert_eq!(100.0, exposure["10"].net());
+        assert_eq!(300.0, exposure["11"].net());
+    }
+
+    #[test]
+    fn test_contract_multiplier() {
+        let option_chain = vec![option_with_gamma(10.0, 0.5)];
+
+        let exposure = gamma_exposure_by_price(&option_chain, &GammaExposureOptions::default());
+        assert_eq!(0.5 * 2.0 * 100.0, exposure["10"].net());
+
+        let options = GammaExposureOptions {
+            contract_multiplier: 1.0,
+            ..Default::default()
+        };
+        let exposure = gamma_exposure_by_price(&option_chain, &options);
+        assert_eq!(0.5 * 2.0, exposure["10"].net());
+
+        let mut option = option_with_gamma(10.0, 0.0);
+        option.expiration_date = (Local::now().date_naive() + chrono::Duration::days(30))
+            .format("%Y-%m-%d")
+            .to_string();
+        option.mid_iv = Some(0.3);
+        let aggregate = |contract_multiplier| {
+            let options = GammaExposureOptions {
+                contract_multiplier,
+                ..Default::default()
+            };
+            gamma_exposure_aggregate("TST", std::slice::from_ref(&option), &options)
+                .unwrap()
+                .total_exposure
+        };
+        assert!((aggregate(100.0) - 100.0 * aggregate(1.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_missing_greeks() {
+        let mut option_chain = vec![
+            option_with_gamma(10.0, 0.5),
+            option_with_gamma(11.0, 0.5),
+            option_with_gamma(12.0, 0.5),
+            option_with_gamma(13.0, 0.5),
+        ];
+        option_chain[1].greeks = None;
+        option_chain[3].greeks = None;
+
+        let options = GammaExposureOptions::default();
+        let coverage = greeks_coverage(&option_chain, &options);
+        assert_eq!(
+            GreeksCoverage {
+                included: 2,
+                missing: 2
+            },
+            coverage
+        );
+        assert_eq!(0.5, coverage.missing_fraction());
+
+        let exposure = gamma_exposure_by_price(&option_chain, &options);
+        assert_eq!(vec!["10", "12"], exposure.keys().collect::<Vec<_>>());
+
+        let options = GammaExposureOptions {
+            max_missing_greeks: Some(0.25),
+            ..Default::default()
+        };
+        assert!(gamma_exposure("TST", &option_chain, &options).is_err());
+
+        let options = GammaExposureOptions {
+            max_missing_greeks: Some(0.5),
+            ..Default::default()
+        };
+        assert!(gamma_exposure("TST", &option_chain, &options).is_ok());
+    }
+
+    #[test]
+    fn test_call_put_split() {
+        let call = option_with_gamma(10.0, 0.5);
+        let mut put = option_with_gamma(10.0, 0.25);
+        put.option_type = OptionType::Put;
+
+        let stats = gamma_exposure("TST", &[call, put], &GammaExposureOptions::default()).unwrap();
+        let price = &stats.prices[0];
+        assert_eq!(100.0, price.call_exposure);
+        assert_eq!(-50.0, price.put_exposure);
+        assert_eq!(50.0, price.gamma_exposure);
+        assert_eq!(
+            price.gamma_exposure,
+            price.call_exposure + price.put_exposure
+        );
+    }
+
+    #[test]
+    fn test_open_interest_and_volume() {
+        let mut call = option_with_gamma(10.0, 0.5);
+        call.open_interest = 30;
+        call.volume = 4;
+        let mut put = option_with_gamma(10.0, 0.25);
+        put.option_type = OptionType::Put;
+        put.open_interest = 12;
+        put.volume = 9;
+        // Beyond max_gamma, so it adds no exposure but still counts.
+        let mut outlier = option_with_gamma(10.0, 10.0);
+        outlier.open_interest = 5;
+        let mut other = option_with_gamma(20.0, 0.5);
+        other.open_interest = 7;
+
+        let option_chain = [call, put, outlier, other];
+        let stats = gamma_exposure("TST", &option_chain, &GammaExposureOptions::default()).unwrap();
+
+        let at_strike: Vec<&OptionInfo> =
+            option_chain.iter().filter(|o| o.strike == 10.0).collect();
+        let price = &stats.prices[0];
+        assert_eq!("10", price.strike);
+        assert_eq!(
+            at_strike.iter().map(|o| o.open_interest).sum::<u64>(),
+            price.open_interest
+        );
+        assert_eq!(
+            at_strike.iter().map(|o| o.volume).sum::<u64>(),
+            price.volume
+        );
+        assert_eq!(47, price.open_interest);
+        assert_eq!(7, stats.prices[1].open_interest);
+    }
+
+    #[test]
+    fn test_option_type_filter() {
+        let call = option_with_gamma(10.0, 0.5);
+        let mut put = option_with_gamma(10.0, 0.25);
+        put.option_type = OptionType::Put;
+        let mut lone_put = option_with_gamma(20.0, 0.25);
+        lone_put.option_type = OptionType::Put;
+        let option_chain = [call, put, lone_put];
+
+        let options = GammaExposureOptions {
+            option_type: Some(OptionType::Call),
+            ..Default::default()
+        };
+        let by_price = gamma_exposure_by_price(&option_chain, &options);
+        assert_eq!(vec!["10"], by_price.keys().collect::<Vec<_>>());
+        assert_eq!(0.0, by_price["10"].put);
+        assert_eq!(option_chain[0].open_interest, by_price["10"].open_interest);
+
+        let stats = gamma_exposure("TST", &option_chain, &options).unwrap();
+        assert!(stats.prices.iter().all(|price| price.put_exposure == 0.0));
+        assert_eq!(100.0, stats.total_exposure);
+
+        let options = GammaExposureOptions {
+            option_type: Some(OptionType::Put),
+            ..Default::default()
+        };
+        let stats = gamma_exposure("TST", &option_chain, &options).unwrap();
+        assert!(stats.prices.iter().all(|price| price.call_exposure == 0.0));
+        assert_eq!(-100.0, stats.total_exposure);
+    }
+
+    #[test]
+    fn test_normalize_strike_spacing() {
+        // Spacing widens from $1 to $5 to $10, as on a real chain.
+        let option_chain: Vec<OptionInfo> = [99.0, 100.0, 105.0, 115.0]
+            .iter()
+            .map(|&strike| option_with_gamma(strike, 0.5))
+            .collect();
+        let options = GammaExposureOptions {
+            normalize_strike_spacing: true,
+            ..Default::default()
+        };
+
+        let raw = gamma_exposure_by_price(&option_chain, &GammaExposureOptions::default());
+        let normalized = gamma_exposure_by_price(&option_chain, &options);
+
+        for (strike, width) in &[("99", 1.0), ("100", 3.0), ("105", 7.5), ("115", 10.0)] {
+            assert_eq!(
+                raw[*strike].call / width,
+                normalized[*strike].call,
+                "{}",
+                strike
+            );
+            assert_eq!(
+                raw[*strike].open_interest,
+                normalized[*strike].open_interest
+            );
+        }
+
+        let lone = gamma_exposure_by_price(&option_chain[..1], &options);
+        assert_eq!(raw["99"], lone["99"]);
+    }
+
+    #[test]
+    fn test_min_open_interest() {
+        let mut call = option_with_gamma(100.0, 0.5);
+        call.open_interest = 30;
+        let mut put = option_with_gamma(100.0, 0.5);
+        put.option_type = OptionType::Put;
+        put.open_interest = 10;
+        let mut thin = option_with_gamma(105.0, 0.5);
+        thin.open_interest = 2;
+        let option_chain = [call, put, thin];
+
+        let by_price = gamma_exposure_by_price(&option_chain, &GammaExposureOptions::default());
+        assert_eq!(2, by_price.len());
+
+        let options = GammaExposureOptions {
+            min_open_interest: 5,
+            ..Default::default()
+        };
+        let by_price = gamma_exposure_by_price(&option_chain, &options);
+        assert_eq!(vec!["100"], by_price.keys().collect::<Vec<_>>());
+        assert_eq!(40, by_price["100"].open_interest);
+
+        let stats = gamma_exposure("TST", &option_chain, &options).unwrap();
+        assert!(stats.prices.iter().all(|price| price.strike != "105"));
+    }
+
+    #[test]
+    fn test_min_open_interest_keeps_full_strike_spacing() {
+        let mut option_chain: Vec<OptionInfo> = [100.0, 101.0, 102.0]
+            .iter()
+            .map(|&strike| option_with_gamma(strike, 0.5))
+            .collect();
+        option_chain[1].open_interest = 1;
+
+        let normalized = GammaExposureOptions {
+            normalize_strike_spacing: true,
+            ..Default::default()
+        };
+        let filtered = GammaExposureOptions {
+            min_open_interest: 2,
+            ..normalized.clone()
+        };
+
+        let all = gamma_exposure_by_price(&option_chain, &normalized);
+        let thick = gamma_exposure_by_price(&option_chain, &filtered);
+        assert!(!thick.contains_key("101"));
+        assert_eq!(all["100"], thick["100"]);
+        assert_eq!(all["102"], thick["102"]);
+    }
 }
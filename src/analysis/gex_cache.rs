@@ -0,0 +1,213 @@
+use std::{collections::HashMap, sync::Mutex, time::Instant};
+
+use chrono::{Local, NaiveDate};
+
+use crate::types::{GammaExposureStats, OptionInfo};
+
+use super::gamma_exposure::GammaExposureOptions;
+
+// Past this many entries, the oldest is dropped to make room.
+const MAX_ENTRIES: usize = 256;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct CacheKey {
+    symbol: String,
+    snapshot: String,
+    // Days to expiration are counted from today, so results expire with it.
+    date: NaiveDate,
+    options: String,
+}
+
+#[derive(Debug)]
+struct Entry {
+    stats: GammaExposureStats,
+    inserted_at: Instant,
+}
+
+/// Memoizes gamma exposure results per symbol, chain snapshot, date and
+/// options. Entries for a symbol are dropped as soon as a newer snapshot is
+/// seen, and the whole cache holds at most `MAX_ENTRIES`.
+#[derive(Debug, Default)]
+pub struct GammaExposureCache {
+    entries: Mutex<HashMap<CacheKey, Entry>>,
+}
+
+impl GammaExposureCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_or_compute(
+        &self,
+        symbol: &str,
+        option_chain: &[OptionInfo],
+        options: &GammaExposureOptions,
+        compute: impl FnOnce() -> anyhow::Result<GammaExposureStats>,
+    ) -> anyhow::Result<GammaExposureStats> {
+        self.get_or_compute_on(
+            Local::now().date_naive(),
+            symbol,
+            option_chain,
+            options,
+            compute,
+        )
+    }
+
+    fn get_or_compute_on(
+        &self,
+        date: NaiveDate,
+        symbol: &str,
+        option_chain: &[OptionInfo],
+        options: &GammaExposureOptions,
+        compute: impl FnOnce() -> anyhow::Result<GammaExposureStats>,
+    ) -> anyhow::Result<GammaExposureStats> {
+        // A live spot would make every banded request a miss, so only the
+        // grid it selects is part of the key.
+        let key = CacheKey {
+            symbol: symbol.to_uppercase(),
+            snapshot: option_chain
+                .first()
+                .map(|o| o.timestamp.clone())
+                .unwrap_or_default(),
+            date,
+            options: format!(
+                "{:?} {:?}",
+                GammaExposureOptions {
+                    spot: None,
+                    ..options.clone()
+                },
+                options.band_steps()
+            ),
+        };
+
+        if let Some(entry) = self.lock().get(&key) {
+            let mut stats = entry.stats.clone();
+            stats.spot_price = options.spot;
+            return Ok(stats);
+        }
+
+        let stats = compute()?;
+
+        let mut entries = self.lock();
+        entries.retain(|k, _| {
+            k.date == key.date && (k.symbol != key.symbol || k.snapshot == key.snapshot)
+        });
+        if entries.len() >= MAX_ENTRIES {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.inserted_at)
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            key,
+            Entry {
+                stats: stats.clone(),
+                inserted_at: Instant::now(),
+            },
+        );
+
+        Ok(stats)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<CacheKey, Entry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_computes_once_per_snapshot() {
+        let cache = GammaExposureCache::new();
+        let options = GammaExposureOptions::default();
+        let mut calls = 0;
+
+        let option_chain = vec![OptionInfo::test()];
+        for _ in 0..2 {
+            cache
+                .get_or_compute("TST", &option_chain, &options, || {
+                    calls += 1;
+                    Ok(GammaExposureStats::test())
+                })
+                .unwrap();
+        }
+        assert_eq!(1, calls);
+
+        let mut refreshed = OptionInfo::test();
+        refreshed.timestamp = "later".to_string();
+        cache
+            .get_or_compute("TST", &[refreshed], &options, || {
+                calls += 1;
+                Ok(GammaExposureStats::test())
+            })
+            .unwrap();
+        assert_eq!(2, calls);
+        assert_eq!(1, cache.lock().len());
+    }
+
+    #[test]
+    fn test_spot_within_the_same_grid_hits() {
+        let cache = GammaExposureCache::new();
+        let option_chain = vec![OptionInfo::test()];
+        let mut calls = 0;
+
+        for spot in [101.1, 101.2, 101.6] {
+            let options = GammaExposureOptions {
+                spot: Some(spot),
+                spot_band: Some(2.0),
+                ..Default::default()
+            };
+            let stats = cache
+                .get_or_compute("TST", &option_chain, &options, || {
+                    calls += 1;
+                    let mut stats = GammaExposureStats::test();
+                    stats.spot_price = Some(spot);
+                    Ok(stats)
+                })
+                .unwrap();
+            assert_eq!(Some(spot), stats.spot_price);
+        }
+        assert_eq!(2, calls);
+    }
+
+    #[test]
+    fn test_new_date_recomputes() {
+        let cache = GammaExposureCache::new();
+        let options = GammaExposureOptions::default();
+        let option_chain = vec![OptionInfo::test()];
+        let mut calls = 0;
+
+        for day in [1, 1, 2] {
+            let date = NaiveDate::from_ymd_opt(2021, 6, day).unwrap();
+            cache
+                .get_or_compute_on(date, "TST", &option_chain, &options, || {
+                    calls += 1;
+                    Ok(GammaExposureStats::test())
+                })
+                .unwrap();
+        }
+        assert_eq!(2, calls);
+        assert_eq!(1, cache.lock().len());
+    }
+
+    #[test]
+    fn test_size_is_capped() {
+        let cache = GammaExposureCache::new();
+        let options = GammaExposureOptions::default();
+        let option_chain = vec![OptionInfo::test()];
+
+        for i in 0..MAX_ENTRIES + 10 {
+            cache
+                .get_or_compute(&format!("S{}", i), &option_chain, &options, || {
+                    Ok(GammaExposureStats::test())
+                })
+                .unwrap();
+        }
+        assert_eq!(MAX_ENTRIES, cache.lock().len());
+    }
+}
@@ -0,0 +1,252 @@
+use crate::types::{Ohlc, OhlcInterval};
+
+/// Simple moving average of closes. `None` until `window` bars have been seen.
+pub fn moving_average(ohlc: &[Ohlc], window: usize) -> Vec<Option<f64>> {
+    let closes: Vec<f64> = ohlc.iter().map(|bar| bar.close).collect();
+    rolling_mean(&closes, window)
+}
+
+fn rolling_mean(values: &[f64], window: usize) -> Vec<Option<f64>> {
+    if window == 0 {
+        return vec![None; values.len()];
+    }
+
+    let mut sum = 0.0;
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            sum += value;
+            if i >= window {
+                sum -= values[i - window];
+            }
+            if i + 1 >= window {
+                Some(sum / window as f64)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+pub const DEFAULT_BOLLINGER_PERIOD: usize = 20;
+pub const DEFAULT_BOLLINGER_STD_DEVS: f64 = 2.0;
+
+/// (lower, middle, upper) bands: the moving average of closes plus and minus
+/// `std_devs` population standard deviations over the same window.
+pub fn bollinger_bands(
+    ohlc: &[Ohlc],
+    period: usize,
+    std_devs: f64,
+) -> Vec<(Option<f64>, Option<f64>, Option<f64>)> {
+    moving_average(ohlc, period)
+        .into_iter()
+        .enumerate()
+        .map(|(i, middle)| match middle {
+            Some(middle) => {
+                let window = &ohlc[i + 1 - period..=i];
+                let variance = window
+                    .iter()
+                    .map(|bar| (bar.close - middle).powi(2))
+                    .sum::<f64>()
+                    / period as f64;
+                let width = std_devs * variance.sqrt();
+                (Some(middle - width), Some(middle), Some(middle + width))
+            }
+            None => (None, None, None),
+        })
+        .collect()
+}
+
+/// Annualized sample standard deviation of log returns of closes. Zero when
+/// there are fewer than two returns.
+pub fn realized_volatility(ohlc: &[Ohlc], annualization: f64) -> f64 {
+    let returns: Vec<f64> = ohlc
+        .windows(2)
+        .map(|w| (w[1].close / w[0].close).ln())
+        .collect();
+    if returns.len() < 2 {
+        return 0.0;
+    }
+
+    let n = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / n;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (variance * annualization).sqrt()
+}
+
+/// Bars per year for annualizing, assuming 252 sessions of 6.5 hours. Ticks
+/// have no fixed spacing.
+pub fn periods_per_year(interval: OhlcInterval) -> Option<f64> {
+    let sessions = 252.0;
+    let minutes = 6.5 * 60.0;
+    Some(match interval {
+        OhlcInterval::Tick => return None,
+        OhlcInterval::OneMinute => sessions * minutes,
+        OhlcInterval::FiveMinute => sessions * minutes / 5.0,
+        OhlcInterval::FifteenMinute => sessions * minutes / 15.0,
+        OhlcInterval::Hourly => sessions * 6.5,
+        OhlcInterval::Daily => sessions,
+        OhlcInterval::Weekly => 52.0,
+        OhlcInterval::Monthly => 12.0,
+    })
+}
+
+pub const DEFAULT_RSI_PERIOD: usize = 14;
+
+/// Relative strength index of close-to-close changes with Wilder's smoothing.
+/// The first value lands on the bar after `period` changes.
+pub fn rsi(ohlc: &[Ohlc], period: usize) -> Vec<Option<f64>> {
+    let mut values = vec![None; ohlc.len()];
+    if period == 0 || ohlc.len() <= period {
+        return values;
+    }
+
+    let changes: Vec<f64> = ohlc.windows(2).map(|w| w[1].close - w[0].close).collect();
+    let gain = |change: f64| change.max(0.0);
+    let loss = |change: f64| (-change).max(0.0);
+
+    let mut average_gain = changes[..period].iter().map(|&c| gain(c)).sum::<f64>() / period as f64;
+    let mut average_loss = changes[..period].iter().map(|&c| loss(c)).sum::<f64>() / period as f64;
+    values[period] = Some(rsi_value(average_gain, average_loss));
+
+    for (i, &change) in changes.iter().enumerate().skip(period) {
+        average_gain = (average_gain * (period - 1) as f64 + gain(change)) / period as f64;
+        average_loss = (average_loss * (period - 1) as f64 + loss(change)) / period as f64;
+        values[i + 1] = Some(rsi_value(average_gain, average_loss));
+    }
+
+    values
+}
+
+fn rsi_value(average_gain: f64, average_loss: f64) -> f64 {
+    if average_loss == 0.0 {
+        if average_gain == 0.0 {
+            50.0
+        } else {
+            100.0
+        }
+    } else {
+        100.0 - 100.0 / (1.0 + average_gain / average_loss)
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn test_bars(closes: &[f64]) -> Vec<Ohlc> {
+    closes
+        .iter()
+        .enumerate()
+        .map(|(i, &close)| Ohlc {
+            interval: crate::types::OhlcInterval::Daily,
+            time: format!("2021-06-{:02}", i + 1),
+            price: close,
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: 100,
+            vwap: None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moving_average() {
+        let bars = test_bars(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(
+            vec![None, None, Some(2.0), Some(3.0), Some(4.0)],
+            moving_average(&bars, 3)
+        );
+    }
+
+    #[test]
+    fn test_moving_average_window_edges() {
+        let bars = test_bars(&[1.0, 2.0, 3.0]);
+        assert_eq!(vec![None, None, Some(2.0)], moving_average(&bars, 3));
+        assert_eq!(vec![None; 3], moving_average(&bars, 4));
+        assert_eq!(vec![None; 3], moving_average(&bars, 0));
+        assert!(moving_average(&[], 3).is_empty());
+    }
+
+    #[test]
+    fn test_rsi_reference_series() {
+        // Wilder's 14 period example as worked through by StockCharts. Their
+        // table rounds the running averages, so it drifts by a few hundredths.
+        let bars = test_bars(&[
+            44.34, 44.09, 44.15, 43.61, 44.33, 44.83, 45.10, 45.42, 45.84, 46.08, 45.89, 46.03,
+            45.61, 46.28, 46.28, 46.00, 46.03, 46.41, 46.22, 45.64, 46.21, 46.25, 45.71, 46.45,
+            45.78, 45.35, 44.03, 44.18, 44.22, 44.57, 43.42, 42.66, 43.13,
+        ]);
+        let expected = [
+            70.53, 66.32, 66.55, 69.41, 66.36, 57.97, 62.93, 63.26, 56.06, 62.38, 54.71, 50.42,
+            39.99, 41.46, 41.87, 45.46, 37.30, 33.08, 37.77,
+        ];
+
+        let values = rsi(&bars, 14);
+
+        assert_eq!(bars.len(), values.len());
+        assert!(values[..14].iter().all(Option::is_none));
+        for (value, expected) in values[14..].iter().zip(&expected) {
+            let value = value.unwrap();
+            assert!((value - expected).abs() < 0.1, "{} != {}", value, expected);
+        }
+    }
+
+    #[test]
+    fn test_rsi_edges() {
+        let rising = test_bars(&[1.0, 2.0, 3.0]);
+        assert_eq!(vec![None, None, Some(100.0)], rsi(&rising, 2));
+        assert_eq!(vec![None, None, Some(50.0)], rsi(&test_bars(&[1.0; 3]), 2));
+        assert_eq!(vec![None; 3], rsi(&rising, 3));
+    }
+
+    #[test]
+    fn test_bollinger_bands() {
+        let bars = test_bars(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+        let bands = bollinger_bands(&bars, 8, 2.0);
+        assert_eq!((None, None, None), bands[6]);
+        assert_eq!((Some(1.0), Some(5.0), Some(9.0)), bands[7]);
+    }
+
+    #[test]
+    fn test_bollinger_bands_widen_with_volatility() {
+        let calm = test_bars(&[100.0, 101.0, 100.0, 101.0, 100.0, 101.0]);
+        let volatile = test_bars(&[100.0, 105.0, 95.0, 106.0, 94.0, 107.0]);
+        let width = |bars: &[Ohlc]| {
+            bollinger_bands(bars, 5, 2.0)
+                .iter()
+                .filter_map(|&(lower, _, upper)| Some(upper? - lower?))
+                .collect::<Vec<_>>()
+        };
+
+        let calm = width(&calm);
+        let volatile = width(&volatile);
+        assert_eq!(2, calm.len());
+        for (calm, volatile) in calm.iter().zip(&volatile) {
+            assert!(volatile > calm, "{} <= {}", volatile, calm);
+        }
+        assert!(volatile[1] > volatile[0]);
+    }
+
+    #[test]
+    fn test_realized_volatility_constant_returns() {
+        let closes: Vec<f64> = (0..10).map(|i| 100.0 * 1.01f64.powi(i)).collect();
+        assert!(realized_volatility(&test_bars(&closes), 252.0) < 1e-9);
+        assert_eq!(0.0, realized_volatility(&test_bars(&[100.0, 101.0]), 252.0));
+    }
+
+    #[test]
+    fn test_realized_volatility() {
+        // Log returns alternate between +0.01 and -0.01, whose sample variance
+        // over four returns is 0.0004 / 3.
+        let up = 0.01f64.exp();
+        let bars = test_bars(&[100.0, 100.0 * up, 100.0, 100.0 * up, 100.0]);
+        let expected = (0.0004 / 3.0 * 252.0f64).sqrt();
+        let vol = realized_volatility(&bars, 252.0);
+        assert!((vol - expected).abs() < 1e-12, "{} != {}", vol, expected);
+    }
+}
@@ -1,10 +1,16 @@
 use crate::types::{
-    stats::{HedgeExposure, StrikeStats},
+    stats::{HedgeExposure, OpenInterestChange, StrikeStats},
     OptionInfo, OptionType,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-pub fn option_stats(option_chain: &[OptionInfo]) -> Vec<StrikeStats> {
+/// Strikes whose spread is wider than this fraction of the mid are flagged
+/// as illiquid.
+pub const ILLIQUID_SPREAD_PERCENT: f64 = 0.1;
+
+/// Per-strike stats, leaving out strikes whose combined call and put open
+/// interest is below `min_open_interest`.
+pub fn option_stats(option_chain: &[OptionInfo], min_open_interest: u64) -> Vec<StrikeStats> {
     let mut strike_to_stats: HashMap<String, StrikeStats> = HashMap::new();
 
     for option in option_chain {
@@ -17,8 +23,18 @@ pub fn option_stats(option_chain: &[OptionInfo]) -> Vec<StrikeStats> {
         let vanna = if vanna.is_finite() { vanna } else { 0.0 };
         let charm = if charm.is_finite() { charm } else { 0.0 };
 
+        let quote = match (option.bid, option.ask) {
+            (Some(bid), Some(ask)) => Some((bid, ask)),
+            _ => None,
+        };
+
         if let Some(aggregate_stats) = strike_to_stats.get_mut(&strike) {
             aggregate_stats.open_interest += option.open_interest;
+            aggregate_stats.volume += option.volume;
+            if let Some((bid, ask)) = quote {
+                aggregate_stats.bid = Some(aggregate_stats.bid.unwrap_or(0.0) + bid);
+                aggregate_stats.ask = Some(aggregate_stats.ask.unwrap_or(0.0) + ask);
+            }
 
             if option.option_type == OptionType::Call {
                 aggregate_stats.call_exposure.gamma += gamma;
@@ -33,6 +49,12 @@ pub fn option_stats(option_chain: &[OptionInfo]) -> Vec<StrikeStats> {
             let stats = StrikeStats {
                 strike: option.strike,
                 open_interest: option.open_interest,
+                volume: option.volume,
+                bid: quote.map(|(bid, _)| bid),
+                ask: quote.map(|(_, ask)| ask),
+                spread: None,
+                spread_percent: None,
+                illiquid: false,
                 call_exposure: HedgeExposure {
                     gamma,
                     vanna,
@@ -49,5 +71,470 @@ pub fn option_stats(option_chain: &[OptionInfo]) -> Vec<StrikeStats> {
         }
     }
 
-    strike_to_stats.into_iter().map(|(_, v)| v).collect()
+    strike_to_stats
+        .into_values()
+        .filter(|stats| stats.open_interest >= min_open_interest)
+        .map(|mut stats| {
+            // Bid and ask are summed over every contract at the strike, so the
+            // percentage is the premium-weighted spread.
+            if let (Some(bid), Some(ask)) = (stats.bid, stats.ask) {
+                let spread = ask - bid;
+                let mid = (ask + bid) / 2.0;
+                stats.spread = Some(spread);
+                if mid > 0.0 {
+                    let spread_percent = spread / mid;
+                    stats.spread_percent = Some(spread_percent);
+                    stats.illiquid = spread_percent > ILLIQUID_SPREAD_PERCENT;
+                }
+            }
+            stats
+        })
+        .collect()
+}
+
+/// The `n` strikes with the most combined call and put volume. Ties go to the
+/// lower strike.
+pub fn most_active(option_chain: &[OptionInfo], n: usize) -> Vec<StrikeStats> {
+    most_active_by(option_chain, n, |s| s.volume)
+}
+
+/// The `n` strikes with the most combined call and put open interest. Ties go
+/// to the lower strike.
+pub fn most_active_by_open_interest(option_chain: &[OptionInfo], n: usize) -> Vec<StrikeStats> {
+    most_active_by(option_chain, n, |s| s.open_interest)
+}
+
+fn most_active_by(
+    option_chain: &[OptionInfo],
+    n: usize,
+    value: impl Fn(&StrikeStats) -> u64,
+) -> Vec<StrikeStats> {
+    let mut stats = option_stats(option_chain, 0);
+    stats.sort_by(|s1, s2| {
+        value(s2)
+            .cmp(&value(s1))
+            .then(s1.strike.total_cmp(&s2.strike))
+    });
+    stats.truncate(n);
+    stats
+}
+
+/// The strike at which option holders would collect the least at expiration.
+pub fn max_pain(option_chain: &[OptionInfo]) -> f64 {
+    let mut strikes: Vec<f64> = option_chain.iter().map(|o| o.strike).collect();
+    strikes.sort_by(|s1, s2| s1.total_cmp(s2));
+    strikes.dedup();
+
+    let payout = |settlement: f64| -> f64 {
+        option_chain
+            .iter()
+            .map(|option| {
+                let intrinsic = match option.option_type {
+                    OptionType::Call => (settlement - option.strike).max(0.0),
+                    OptionType::Put => (option.strike - settlement).max(0.0),
+                };
+                intrinsic * option.open_interest as f64
+            })
+            .sum()
+    };
+
+    let mut max_pain = 0.0;
+    let mut min_payout = f64::MAX;
+    for strike in strikes {
+        let payout = payout(strike);
+        if payout < min_payout {
+            min_payout = payout;
+            max_pain = strike;
+        }
+    }
+
+    max_pain
+}
+
+/// Total put open interest over total call open interest. `None` when there
+/// is no call open interest to divide by.
+pub fn put_call_ratio(option_chain: &[OptionInfo]) -> Option<f64> {
+    put_call_ratio_by(option_chain, |o| o.open_interest)
+}
+
+/// Same as `put_call_ratio` but from traded volume, so `None` on days with
+/// no call volume.
+pub fn put_call_volume_ratio(option_chain: &[OptionInfo]) -> Option<f64> {
+    put_call_ratio_by(option_chain, |o| o.volume)
+}
+
+fn put_call_ratio_by(
+    option_chain: &[OptionInfo],
+    value: impl Fn(&OptionInfo) -> u64,
+) -> Option<f64> {
+    let (mut put_total, mut call_total) = (0, 0);
+    for option in option_chain {
+        match option.option_type {
+            OptionType::Call => call_total += value(option),
+            OptionType::Put => put_total += value(option),
+        }
+    }
+
+    if call_total == 0 {
+        None
+    } else {
+        Some(put_total as f64 / call_total as f64)
+    }
+}
+
+pub const DEFAULT_SKEW_OTM_PERCENT: f64 = 0.05;
+
+/// Implied volatility of the put nearest `otm_percent` below spot minus that
+/// of the call nearest the same distance above spot.
+pub fn iv_skew(option_chain: &[OptionInfo], current_price: f64, otm_percent: f64) -> Option<f64> {
+    let nearest_iv = |option_type: OptionType, target: f64| {
+        option_chain
+            .iter()
+            .filter(|o| o.option_type == option_type && o.mid_iv.is_some())
+            .min_by(|o1, o2| {
+                (o1.strike - target)
+                    .abs()
+                    .total_cmp(&(o2.strike - target).abs())
+            })
+            .and_then(|o| o.mid_iv)
+    };
+
+    let put_iv = nearest_iv(OptionType::Put, current_price * (1.0 - otm_percent))?;
+    let call_iv = nearest_iv(OptionType::Call, current_price * (1.0 + otm_percent))?;
+    Some(put_iv - call_iv)
+}
+
+/// ATM implied volatility per expiration, ordered by date. ATM is the strike
+/// nearest spot, averaging the call and put there when both have an IV.
+pub fn iv_term_structure(option_chain: &[OptionInfo], current_price: f64) -> Vec<(String, f64)> {
+    let mut expiration_to_options: BTreeMap<&str, Vec<&OptionInfo>> = BTreeMap::new();
+    for option in option_chain.iter().filter(|o| o.mid_iv.is_some()) {
+        expiration_to_options
+            .entry(&option.expiration_date)
+            .or_default()
+            .push(option);
+    }
+
+    expiration_to_options
+        .into_iter()
+        .filter_map(|(expiration_date, options)| {
+            let atm_strike = options.iter().map(|o| o.strike).min_by(|s1, s2| {
+                (s1 - current_price)
+                    .abs()
+                    .total_cmp(&(s2 - current_price).abs())
+            })?;
+            let ivs: Vec<f64> = options
+                .iter()
+                .filter(|o| o.strike == atm_strike)
+                .filter_map(|o| o.mid_iv)
+                .collect();
+            let iv = ivs.iter().sum::<f64>() / ivs.len() as f64;
+            Some((expiration_date.to_string(), iv))
+        })
+        .collect()
+}
+
+/// Implied volatility per expiration averaged over every strike with an IV,
+/// weighted by open interest. Expirations without open interest are left out.
+pub fn oi_weighted_iv(option_chain: &[OptionInfo]) -> Vec<(String, f64)> {
+    let mut expiration_to_sums: BTreeMap<&str, (f64, u64)> = BTreeMap::new();
+    for option in option_chain {
+        if let Some(iv) = option.mid_iv {
+            let (weighted_iv, open_interest) = expiration_to_sums
+                .entry(&option.expiration_date)
+                .or_default();
+            *weighted_iv += iv * option.open_interest as f64;
+            *open_interest += option.open_interest;
+        }
+    }
+
+    expiration_to_sums
+        .into_iter()
+        .filter(|(_, (_, open_interest))| *open_interest > 0)
+        .map(|(expiration_date, (weighted_iv, open_interest))| {
+            (
+                expiration_date.to_string(),
+                weighted_iv / open_interest as f64,
+            )
+        })
+        .collect()
+}
+
+/// Per-strike open interest change between two snapshots of a chain. Strikes
+/// missing from one snapshot count as zero open interest there.
+pub fn oi_change(from: &[OptionInfo], to: &[OptionInfo]) -> Vec<OpenInterestChange> {
+    let mut strikes: BTreeMap<String, OpenInterestChange> = BTreeMap::new();
+
+    let mut add = |option: &OptionInfo, sign: i64| {
+        let change =
+            strikes
+                .entry(option.strike.to_string())
+                .or_insert_with(|| OpenInterestChange {
+                    strike: option.strike,
+                    open_interest: 0,
+                    change: 0,
+                    call_change: 0,
+                    put_change: 0,
+                });
+        let delta = sign * option.open_interest as i64;
+
+        change.change += delta;
+        match option.option_type {
+            OptionType::Call => change.call_change += delta,
+            OptionType::Put => change.put_change += delta,
+        }
+        if sign > 0 {
+            change.open_interest += option.open_interest;
+        }
+    };
+
+    for option in from {
+        add(option, -1);
+    }
+    for option in to {
+        add(option, 1);
+    }
+
+    let mut changes: Vec<_> = strikes.into_values().collect();
+    changes.sort_by(|a, b| a.strike.total_cmp(&b.strike));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn option(strike: f64, option_type: OptionType, open_interest: u64) -> OptionInfo {
+        let mut option = OptionInfo::test();
+        option.strike = strike;
+        option.option_type = option_type;
+        option.open_interest = open_interest;
+        option
+    }
+
+    #[test]
+    fn test_oi_change() {
+        let from = vec![
+            option(90.0, OptionType::Call, 100),
+            option(100.0, OptionType::Call, 50),
+            option(100.0, OptionType::Put, 80),
+            option(110.0, OptionType::Put, 30),
+        ];
+        let to = vec![
+            option(100.0, OptionType::Call, 150),
+            option(100.0, OptionType::Put, 60),
+            option(110.0, OptionType::Put, 30),
+            option(120.0, OptionType::Call, 25),
+        ];
+
+        let changes = oi_change(&from, &to);
+
+        let strikes: Vec<f64> = changes.iter().map(|c| c.strike).collect();
+        assert_eq!(vec![90.0, 100.0, 110.0, 120.0], strikes);
+
+        assert_eq!(0, changes[0].open_interest);
+        assert_eq!(-100, changes[0].change);
+        assert_eq!(-100, changes[0].call_change);
+
+        assert_eq!(210, changes[1].open_interest);
+        assert_eq!(80, changes[1].change);
+        assert_eq!(100, changes[1].call_change);
+        assert_eq!(-20, changes[1].put_change);
+
+        assert_eq!(30, changes[2].open_interest);
+        assert_eq!(0, changes[2].change);
+
+        assert_eq!(25, changes[3].open_interest);
+        assert_eq!(25, changes[3].change);
+        assert_eq!(0, changes[3].put_change);
+
+        assert!(oi_change(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn test_max_pain() {
+        let option_chain = vec![
+            option(90.0, OptionType::Call, 100),
+            option(100.0, OptionType::Call, 10),
+            option(100.0, OptionType::Put, 10),
+            option(110.0, OptionType::Put, 100),
+        ];
+        assert_eq!(100.0, max_pain(&option_chain));
+        assert_eq!(0.0, max_pain(&[]));
+    }
+
+    #[test]
+    fn test_put_call_ratio() {
+        let option_chain = vec![
+            option(90.0, OptionType::Call, 100),
+            option(100.0, OptionType::Call, 60),
+            option(100.0, OptionType::Put, 40),
+            option(110.0, OptionType::Put, 200),
+        ];
+        assert_eq!(Some(1.5), put_call_ratio(&option_chain));
+        assert_eq!(None, put_call_ratio(&option_chain[2..]));
+        assert_eq!(None, put_call_ratio(&[]));
+    }
+
+    #[test]
+    fn test_put_call_volume_ratio() {
+        let mut option_chain = vec![
+            option(100.0, OptionType::Call, 100),
+            option(100.0, OptionType::Put, 100),
+            option(110.0, OptionType::Put, 100),
+        ];
+        option_chain[0].volume = 40;
+        option_chain[1].volume = 10;
+        option_chain[2].volume = 20;
+        assert_eq!(Some(0.75), put_call_volume_ratio(&option_chain));
+
+        option_chain[0].volume = 0;
+        assert_eq!(None, put_call_volume_ratio(&option_chain));
+    }
+
+    #[test]
+    fn test_iv_skew() {
+        let surface = [
+            (90.0, 0.35),
+            (95.0, 0.30),
+            (100.0, 0.25),
+            (105.0, 0.22),
+            (110.0, 0.20),
+        ];
+        let option_chain: Vec<OptionInfo> = surface
+            .iter()
+            .flat_map(|(strike, iv)| {
+                let mut call = option(*strike, OptionType::Call, 1);
+                let mut put = option(*strike, OptionType::Put, 1);
+                call.mid_iv = Some(*iv);
+                put.mid_iv = Some(*iv);
+                vec![call, put]
+            })
+            .collect();
+
+        let skew = iv_skew(&option_chain, 100.0, 0.05).unwrap();
+        assert!((skew - 0.08).abs() < 1e-12);
+        let skew = iv_skew(&option_chain, 100.0, 0.1).unwrap();
+        assert!((skew - 0.15).abs() < 1e-12);
+        assert_eq!(None, iv_skew(&option_chain[..0], 100.0, 0.05));
+    }
+
+    #[test]
+    fn test_iv_term_structure() {
+        let option = |expiration_date: &str, strike, option_type, iv| {
+            let mut option = option(strike, option_type, 1);
+            option.expiration_date = expiration_date.to_string();
+            option.mid_iv = Some(iv);
+            option
+        };
+        let option_chain = vec![
+            option("2021-03-19", 100.0, OptionType::Call, 0.30),
+            option("2021-03-19", 105.0, OptionType::Call, 0.25),
+            option("2021-03-19", 105.0, OptionType::Put, 0.375),
+            option("2021-01-15", 95.0, OptionType::Put, 0.40),
+            option("2021-01-15", 105.0, OptionType::Call, 0.20),
+        ];
+
+        assert_eq!(
+            vec![
+                ("2021-01-15".to_string(), 0.20),
+                ("2021-03-19".to_string(), 0.3125),
+            ],
+            iv_term_structure(&option_chain, 104.0)
+        );
+    }
+
+    #[test]
+    fn test_oi_weighted_iv() {
+        let option = |expiration_date: &str, strike, open_interest, iv| {
+            let mut option = option(strike, OptionType::Call, open_interest);
+            option.expiration_date = expiration_date.to_string();
+            option.mid_iv = iv;
+            option
+        };
+        let option_chain = vec![
+            option("2021-03-19", 100.0, 300, Some(0.20)),
+            option("2021-03-19", 110.0, 100, Some(0.40)),
+            option("2021-03-19", 120.0, 500, None),
+            option("2021-01-15", 100.0, 0, Some(0.30)),
+        ];
+
+        assert_eq!(
+            vec![("2021-03-19".to_string(), 0.25)],
+            oi_weighted_iv(&option_chain)
+        );
+    }
+
+    #[test]
+    fn test_most_active() {
+        let option = |strike, option_type, open_interest, volume| {
+            let mut option = option(strike, option_type, open_interest);
+            option.volume = volume;
+            option
+        };
+        let option_chain = vec![
+            option(110.0, OptionType::Call, 5, 30),
+            option(100.0, OptionType::Call, 50, 10),
+            option(100.0, OptionType::Put, 50, 20),
+            option(90.0, OptionType::Put, 10, 30),
+            option(80.0, OptionType::Put, 100, 1),
+        ];
+
+        let strikes = |stats: Vec<StrikeStats>| stats.iter().map(|s| s.strike).collect::<Vec<_>>();
+        assert_eq!(
+            vec![90.0, 100.0, 110.0],
+            strikes(most_active(&option_chain, 3))
+        );
+        assert_eq!(
+            vec![80.0, 100.0],
+            strikes(most_active_by_open_interest(&option_chain, 2))
+        );
+        assert_eq!(4, most_active(&option_chain, 10).len());
+    }
+
+    #[test]
+    fn test_min_open_interest() {
+        let option_chain = vec![
+            option(100.0, OptionType::Call, 40),
+            option(100.0, OptionType::Put, 20),
+            option(105.0, OptionType::Call, 3),
+            option(105.0, OptionType::Put, 1),
+        ];
+
+        assert_eq!(2, option_stats(&option_chain, 0).len());
+        assert_eq!(2, option_stats(&option_chain, 4).len());
+
+        let stats = option_stats(&option_chain, 5);
+        assert_eq!(1, stats.len());
+        assert_eq!(100.0, stats[0].strike);
+        assert_eq!(60, stats[0].open_interest);
+    }
+
+    #[test]
+    fn test_spread() {
+        let option = |strike, option_type, bid, ask| {
+            let mut option = option(strike, option_type, 1);
+            option.bid = bid;
+            option.ask = ask;
+            option
+        };
+        let option_chain = vec![
+            option(100.0, OptionType::Call, Some(1.9), Some(2.1)),
+            option(100.0, OptionType::Put, Some(2.85), Some(3.15)),
+            option(110.0, OptionType::Call, Some(0.5), Some(1.0)),
+            option(120.0, OptionType::Call, None, Some(1.0)),
+        ];
+        let mut stats = option_stats(&option_chain, 0);
+        stats.sort_by(|s1, s2| s1.strike.total_cmp(&s2.strike));
+
+        assert!((stats[0].spread.unwrap() - 0.5).abs() < 1e-12);
+        assert!((stats[0].spread_percent.unwrap() - 0.1).abs() < 1e-12);
+        assert!(!stats[0].illiquid);
+
+        assert!((stats[1].spread_percent.unwrap() - 0.5 / 0.75).abs() < 1e-12);
+        assert!(stats[1].illiquid);
+
+        assert_eq!(None, stats[2].spread);
+        assert!(!stats[2].illiquid);
+    }
 }
@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+
+use chrono::Local;
+
+use crate::{
+    math::bs::vanna,
+    types::{GammaExposureStats, OptionInfo},
+};
+
+use super::gamma_exposure::{parse_date, GammaExposureOptions, StrikeExposure};
+
+pub fn vanna_exposure_by_price(
+    option_chain: &[OptionInfo],
+    current_price: f64,
+    options: &GammaExposureOptions,
+) -> anyhow::Result<BTreeMap<String, StrikeExposure>> {
+    let now = Local::now().date_naive();
+    let mut strike_to_vanna_exposure: BTreeMap<String, StrikeExposure> = BTreeMap::new();
+
+    for option in option_chain.iter().filter(|o| options.includes(o)) {
+        let expiration_date = parse_date(&option.expiration_date)?;
+        let days_remaining = expiration_date.signed_duration_since(now).num_days();
+
+        let vanna = vanna(
+            option.mid_iv.unwrap_or(0.0),
+            days_remaining as f64 / 365.0,
+            current_price,
+            option.strike,
+            options.risk_free_rate,
+            options.dividend_yield,
+        );
+        let exposure = if vanna.is_finite() {
+            vanna * option.open_interest as f64 * options.contract_multiplier
+        } else {
+            0.0
+        };
+
+        strike_to_vanna_exposure
+            .entry(option.strike.to_string())
+            .or_default()
+            .add(option.option_type, exposure);
+    }
+
+    Ok(strike_to_vanna_exposure)
+}
+
+pub fn vanna_exposure(
+    symbol: &str,
+    option_chain: &[OptionInfo],
+    current_price: f64,
+    options: &GammaExposureOptions,
+) -> anyhow::Result<GammaExposureStats> {
+    let strike_to_vanna_exposure = vanna_exposure_by_price(option_chain, current_price, options)?;
+    let mut stats = GammaExposureStats::new(symbol, &strike_to_vanna_exposure)?;
+    stats.spot_price = Some(current_price);
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analysis::gamma_exposure::CONTRACT_MULTIPLIER, types::OptionType};
+
+    fn option(strike: f64, option_type: OptionType) -> OptionInfo {
+        let mut option = OptionInfo::test();
+        option.strike = strike;
+        option.option_type = option_type;
+        option.expiration_date = (Local::now().date_naive() + chrono::Duration::days(30))
+            .format("%Y-%m-%d")
+            .to_string();
+        option.mid_iv = Some(0.3);
+        option
+    }
+
+    #[test]
+    fn test_vanna_sign_around_spot() {
+        let option_chain = vec![
+            option(80.0, OptionType::Call),
+            option(120.0, OptionType::Call),
+            option(120.0, OptionType::Put),
+            option(130.0, OptionType::Put),
+        ];
+        let exposure =
+            vanna_exposure_by_price(&option_chain, 100.0, &GammaExposureOptions::default())
+                .unwrap();
+
+        assert!(exposure["80"].call < 0.0);
+        assert!(exposure["120"].call > 0.0);
+        assert!(exposure["120"].put < 0.0);
+        assert_eq!(0.0, exposure["120"].net());
+        assert!(exposure["130"].net() < 0.0);
+
+        let per_contract = GammaExposureOptions {
+            contract_multiplier: 1.0,
+            ..Default::default()
+        };
+        let per_contract = vanna_exposure_by_price(&option_chain, 100.0, &per_contract).unwrap();
+        assert_eq!(
+            per_contract["80"].call * CONTRACT_MULTIPLIER,
+            exposure["80"].call
+        );
+    }
+}
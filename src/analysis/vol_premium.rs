@@ -0,0 +1,70 @@
+use crate::types::{Ohlc, OptionInfo};
+
+use super::{indicators::realized_volatility, option_stats::iv_term_structure};
+
+const TRADING_DAYS: f64 = 252.0;
+
+/// Front-expiration ATM implied volatility minus realized volatility of the
+/// daily bars in `ohlc`, with the last close as spot. Positive when options
+/// are priced richer than the underlying has been moving.
+pub fn vol_premium(
+    symbol: &str,
+    option_chain: &[OptionInfo],
+    ohlc: &[Ohlc],
+) -> anyhow::Result<f64> {
+    if ohlc.len() < 3 {
+        anyhow::bail!("Not enough bars for {} to measure volatility", symbol);
+    }
+    let spot = ohlc[ohlc.len() - 1].close;
+
+    let (_, implied) = iv_term_structure(option_chain, spot)
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("No implied volatility for {}", symbol))?;
+
+    Ok(implied - realized_volatility(ohlc, TRADING_DAYS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analysis::indicators::test_bars, types::OptionType};
+
+    fn option(expiration_date: &str, strike: f64, option_type: OptionType, iv: f64) -> OptionInfo {
+        let mut option = OptionInfo::test();
+        option.expiration_date = expiration_date.to_string();
+        option.strike = strike;
+        option.option_type = option_type;
+        option.mid_iv = Some(iv);
+        option
+    }
+
+    #[test]
+    fn test_vol_premium() {
+        let option_chain = vec![
+            option("2021-07-16", 100.0, OptionType::Call, 0.26),
+            option("2021-07-16", 100.0, OptionType::Put, 0.30),
+            option("2021-07-16", 110.0, OptionType::Call, 0.50),
+            option("2021-08-20", 100.0, OptionType::Call, 0.90),
+        ];
+        // Alternating +/-1% log returns, whose realized vol is sqrt(0.0004 / 3 * 252).
+        let up = 0.01f64.exp();
+        let ohlc = test_bars(&[100.0, 100.0 * up, 100.0, 100.0 * up, 100.0]);
+        let realized = (0.0004 / 3.0 * 252.0f64).sqrt();
+
+        let premium = vol_premium("TST", &option_chain, &ohlc).unwrap();
+        assert!((premium - (0.28 - realized)).abs() < 1e-12);
+        assert!(premium > 0.0);
+
+        let calm = test_bars(&[100.0; 5]);
+        assert!((vol_premium("TST", &option_chain, &calm).unwrap() - 0.28).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_vol_premium_errors() {
+        let ohlc = test_bars(&[100.0; 5]);
+        assert!(vol_premium("TST", &[], &ohlc).is_err());
+        let option_chain = vec![option("2021-07-16", 100.0, OptionType::Call, 0.2)];
+        assert!(vol_premium("TST", &option_chain, &ohlc[..2]).is_err());
+    }
+}
@@ -0,0 +1,66 @@
+use std::{collections::HashSet, sync::Arc};
+
+use warp::{Filter, Rejection};
+
+const API_KEYS_ENV: &str = "API_KEYS";
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+#[derive(Debug)]
+pub struct Unauthorized;
+
+impl warp::reject::Reject for Unauthorized {}
+
+pub type ApiKeys = Arc<HashSet<String>>;
+
+/// Comma-separated keys from `API_KEYS`. Unset or empty leaves the server open.
+pub fn api_keys_from_env() -> Option<ApiKeys> {
+    parse_api_keys(&std::env::var(API_KEYS_ENV).ok()?)
+}
+
+fn parse_api_keys(keys: &str) -> Option<ApiKeys> {
+    let keys: HashSet<String> = keys
+        .split(',')
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(String::from)
+        .collect();
+
+    if keys.is_empty() {
+        None
+    } else {
+        Some(Arc::new(keys))
+    }
+}
+
+/// Rejects with `Unauthorized` unless the `x-api-key` header holds one of the
+/// keys. Passes everything through when no keys are configured.
+pub fn api_key(keys: Option<ApiKeys>) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>(API_KEY_HEADER)
+        .and_then(move |key: Option<String>| {
+            let keys = keys.clone();
+            async move {
+                match (keys, key) {
+                    (None, _) => Ok(()),
+                    (Some(keys), Some(key)) if keys.contains(&key) => Ok(()),
+                    _ => Err(warp::reject::custom(Unauthorized)),
+                }
+            }
+        })
+        .untuple_one()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_api_keys() {
+        let keys = parse_api_keys(" first, second ,,").unwrap();
+        assert_eq!(2, keys.len());
+        assert!(keys.contains("first"));
+        assert!(keys.contains("second"));
+
+        assert!(parse_api_keys("").is_none());
+        assert!(parse_api_keys(" , ").is_none());
+    }
+}
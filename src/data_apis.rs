@@ -1,2 +1,180 @@
+use std::{str::FromStr, time::Duration};
+
+use async_graphql::Enum;
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use reqwest::Client;
+
+use crate::types::{Ohlc, OhlcInterval, OptionInfo, Quote};
+
+pub mod polygon;
 pub mod td;
 pub mod tradier;
+pub mod yahoo;
+
+const PROVIDER_ENV: &str = "MARKET_DATA_PROVIDER";
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The provider doesn't recognise the symbol, or has no options for it.
+#[derive(Debug)]
+pub enum SymbolError {
+    Unknown(String),
+    NoOptions(String),
+}
+
+impl std::fmt::Display for SymbolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SymbolError::Unknown(symbol) => write!(f, "Unknown symbol: {}", symbol),
+            SymbolError::NoOptions(symbol) => write!(f, "No options listed for {}", symbol),
+        }
+    }
+}
+
+impl std::error::Error for SymbolError {}
+
+/// A source of market data. Providers that can't serve a kind of data return
+/// an error for it.
+#[async_trait]
+pub trait MarketDataProvider: Send + Sync {
+    async fn get_quote(&self, symbol: &str) -> anyhow::Result<Quote>;
+
+    /// Bars between `start` and `end`, inclusive. Without a start, the
+    /// provider's default lookback for the interval is used; without an end,
+    /// bars run up to now.
+    async fn get_ohlc(
+        &self,
+        symbol: &str,
+        interval: OhlcInterval,
+        start: Option<NaiveDate>,
+        end: Option<NaiveDate>,
+    ) -> anyhow::Result<Vec<Ohlc>>;
+
+    async fn get_option_chain(&self, symbol: &str) -> anyhow::Result<Vec<OptionInfo>>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+pub enum Provider {
+    #[graphql(name = "tradier")]
+    Tradier,
+    #[graphql(name = "polygon")]
+    Polygon,
+    #[graphql(name = "yahoo")]
+    Yahoo,
+}
+
+impl Provider {
+    pub fn from_env() -> Self {
+        match std::env::var(PROVIDER_ENV) {
+            Ok(provider) => provider.parse().unwrap_or_else(|e| {
+                log::warn!("{}", e);
+                Provider::Tradier
+            }),
+            Err(_) => Provider::Tradier,
+        }
+    }
+
+    // Polygon and Yahoo have no option chains, so theirs come from Tradier.
+    pub fn client(self) -> Box<dyn MarketDataProvider> {
+        match self {
+            Provider::Tradier => Box::new(tradier::Tradier),
+            Provider::Polygon => {
+                Box::new(WithOptionChains::new(polygon::Polygon, tradier::Tradier))
+            }
+            Provider::Yahoo => Box::new(WithOptionChains::new(yahoo::Yahoo, tradier::Tradier)),
+        }
+    }
+}
+
+/// Quotes and bars from `provider`, option chains from `chains`.
+pub struct WithOptionChains<P, C> {
+    provider: P,
+    chains: C,
+}
+
+impl<P, C> WithOptionChains<P, C> {
+    pub fn new(provider: P, chains: C) -> Self {
+        Self { provider, chains }
+    }
+}
+
+#[async_trait]
+impl<P, C> MarketDataProvider for WithOptionChains<P, C>
+where
+    P: MarketDataProvider,
+    C: MarketDataProvider,
+{
+    async fn get_quote(&self, symbol: &str) -> anyhow::Result<Quote> {
+        self.provider.get_quote(symbol).await
+    }
+
+    async fn get_ohlc(
+        &self,
+        symbol: &str,
+        interval: OhlcInterval,
+        start: Option<NaiveDate>,
+        end: Option<NaiveDate>,
+    ) -> anyhow::Result<Vec<Ohlc>> {
+        self.provider.get_ohlc(symbol, interval, start, end).await
+    }
+
+    async fn get_option_chain(&self, symbol: &str) -> anyhow::Result<Vec<OptionInfo>> {
+        self.chains.get_option_chain(symbol).await
+    }
+}
+
+impl FromStr for Provider {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "tradier" => Provider::Tradier,
+            "polygon" => Provider::Polygon,
+            "yahoo" => Provider::Yahoo,
+            _ => anyhow::bail!("Invalid provider: {}", s),
+        })
+    }
+}
+
+/// A client for a provider's shared static, timing out after the seconds in
+/// `timeout_env` or 10 by default.
+pub(crate) fn client_from_env(timeout_env: &str) -> Client {
+    build_client(timeout(timeout_env))
+}
+
+pub(crate) fn build_client(timeout: Duration) -> Client {
+    Client::builder()
+        .timeout(timeout)
+        .build()
+        .unwrap_or_else(|e| {
+            log::error!("Failed to build client with timeout: {}", e);
+            Client::new()
+        })
+}
+
+fn timeout(timeout_env: &str) -> Duration {
+    timeout_from(timeout_env, std::env::var(timeout_env).ok().as_deref())
+}
+
+fn timeout_from(timeout_env: &str, seconds: Option<&str>) -> Duration {
+    match seconds.map(str::parse) {
+        Some(Ok(seconds)) => Duration::from_secs(seconds),
+        Some(Err(e)) => {
+            log::warn!("Invalid {}: {}", timeout_env, e);
+            DEFAULT_TIMEOUT
+        }
+        None => DEFAULT_TIMEOUT,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeout_from() {
+        assert_eq!(Duration::from_secs(3), timeout_from("TIMEOUT", Some("3")));
+        assert_eq!(DEFAULT_TIMEOUT, timeout_from("TIMEOUT", Some("soon")));
+        assert_eq!(DEFAULT_TIMEOUT, timeout_from("TIMEOUT", None));
+    }
+}
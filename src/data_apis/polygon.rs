@@ -0,0 +1,58 @@
+mod get_aggregates;
+mod get_quote;
+
+pub use get_aggregates::get_aggregates;
+pub use get_quote::get_quote;
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use once_cell::sync::Lazy;
+use reqwest::Client;
+
+use super::{client_from_env, MarketDataProvider};
+use crate::types::{Ohlc, OhlcInterval, OptionInfo, Quote};
+
+const API_KEY_ENV: &str = "POLYGON_API_KEY";
+const BASE_URL: &str = "https://api.polygon.io";
+const TIMEOUT_ENV: &str = "POLYGON_TIMEOUT_SECS";
+
+static CLIENT: Lazy<Client> = Lazy::new(|| client_from_env(TIMEOUT_ENV));
+
+async fn get(url: &str) -> anyhow::Result<String> {
+    let api_key = std::env::var(API_KEY_ENV)?;
+
+    let body = CLIENT
+        .get(url)
+        .header("Accept", "application/json")
+        .header("Authorization", format!("Bearer {}", api_key))
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    Ok(body)
+}
+
+pub struct Polygon;
+
+#[async_trait]
+impl MarketDataProvider for Polygon {
+    async fn get_quote(&self, symbol: &str) -> anyhow::Result<Quote> {
+        get_quote(symbol).await
+    }
+
+    async fn get_ohlc(
+        &self,
+        symbol: &str,
+        interval: OhlcInterval,
+        start: Option<NaiveDate>,
+        end: Option<NaiveDate>,
+    ) -> anyhow::Result<Vec<Ohlc>> {
+        get_aggregates(symbol, interval, start, end).await
+    }
+
+    async fn get_option_chain(&self, _symbol: &str) -> anyhow::Result<Vec<OptionInfo>> {
+        anyhow::bail!("Polygon doesn't provide option chains")
+    }
+}
@@ -0,0 +1,145 @@
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::America::New_York;
+use serde::Deserialize;
+
+use crate::types::{Ohlc, OhlcInterval};
+
+pub async fn get_aggregates(
+    symbol: &str,
+    interval: OhlcInterval,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+) -> anyhow::Result<Vec<Ohlc>> {
+    let (multiplier, timespan, lookback_days) = match interval {
+        OhlcInterval::Tick => anyhow::bail!("Polygon aggregates don't support tick bars"),
+        OhlcInterval::OneMinute => (1, "minute", 5),
+        OhlcInterval::FiveMinute => (5, "minute", 5),
+        OhlcInterval::FifteenMinute => (15, "minute", 5),
+        OhlcInterval::Hourly => (1, "hour", 30),
+        OhlcInterval::Daily => (1, "day", 365),
+        OhlcInterval::Weekly => (1, "week", 5 * 365),
+        OhlcInterval::Monthly => (1, "month", 10 * 365),
+    };
+
+    let to = end.unwrap_or_else(|| Utc::now().date_naive());
+    let from = start.unwrap_or(to - Duration::days(lookback_days));
+    let url = aggregates_url(symbol, multiplier, timespan, from, to);
+
+    let body = super::get(&url).await?;
+
+    parse_aggregates(&body, interval)
+}
+
+fn aggregates_url(
+    symbol: &str,
+    multiplier: u32,
+    timespan: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> String {
+    format!(
+        "{}/v2/aggs/ticker/{}/range/{}/{}/{}/{}?adjusted=true&sort=asc&limit=50000",
+        super::BASE_URL,
+        symbol.to_uppercase(),
+        multiplier,
+        timespan,
+        from,
+        to
+    )
+}
+
+fn parse_aggregates(body: &str, interval: OhlcInterval) -> anyhow::Result<Vec<Ohlc>> {
+    let aggregates: AggregatesResponse = serde_json::from_str(body).map_err(|e| {
+        log::error!("{}", e);
+        log::error!("{}", body);
+        e
+    })?;
+
+    aggregates
+        .results
+        .unwrap_or_default()
+        .into_iter()
+        .map(|bar| {
+            let time = New_York
+                .timestamp_millis_opt(bar.t)
+                .single()
+                .ok_or_else(|| anyhow::anyhow!("Invalid timestamp: {}", bar.t))?;
+
+            Ok(Ohlc {
+                interval,
+                time: time.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                price: bar.c,
+                open: bar.o,
+                high: bar.h,
+                low: bar.l,
+                close: bar.c,
+                volume: bar.v as u64,
+                vwap: bar.vw,
+            })
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct AggregatesResponse {
+    results: Option<Vec<Aggregate>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Aggregate {
+    o: f64,
+    h: f64,
+    l: f64,
+    c: f64,
+    v: f64,
+    vw: Option<f64>,
+    t: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_aggregates() {
+        let ohlc = parse_aggregates(
+            include_str!("fixtures/aggregates.json"),
+            OhlcInterval::FiveMinute,
+        )
+        .unwrap();
+
+        assert_eq!(3, ohlc.len());
+        assert_eq!("2021-06-01T09:30:00", ohlc[0].time);
+        assert_eq!("2021-06-01T09:40:00", ohlc[2].time);
+        assert_eq!(422.57, ohlc[0].open);
+        assert_eq!(421.9, ohlc[0].close);
+        assert_eq!(ohlc[0].close, ohlc[0].price);
+        assert_eq!(1203045, ohlc[0].volume);
+        assert_eq!(Some(422.1377), ohlc[0].vwap);
+        assert_eq!(OhlcInterval::FiveMinute, ohlc[0].interval);
+    }
+
+    #[test]
+    fn test_aggregates_url() {
+        let url = aggregates_url(
+            "spy",
+            5,
+            "minute",
+            NaiveDate::from_ymd_opt(2021, 6, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2021, 6, 4).unwrap(),
+        );
+        assert_eq!(
+            "https://api.polygon.io/v2/aggs/ticker/SPY/range/5/minute/2021-06-01/2021-06-04?adjusted=true&sort=asc&limit=50000",
+            url
+        );
+    }
+
+    #[test]
+    fn test_parse_empty_aggregates() {
+        let body =
+            r#"{"ticker":"SPY","queryCount":0,"resultsCount":0,"adjusted":true,"status":"OK"}"#;
+        assert!(parse_aggregates(body, OhlcInterval::Daily)
+            .unwrap()
+            .is_empty());
+    }
+}
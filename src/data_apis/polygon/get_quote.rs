@@ -0,0 +1,80 @@
+use serde::Deserialize;
+
+use crate::types::Quote;
+
+pub async fn get_quote(symbol: &str) -> anyhow::Result<Quote> {
+    let url = format!(
+        "{}/v2/snapshot/locale/us/markets/stocks/tickers/{}",
+        super::BASE_URL,
+        symbol.to_uppercase()
+    );
+
+    let body = super::get(&url).await?;
+
+    parse_quote(&body)
+}
+
+fn parse_quote(body: &str) -> anyhow::Result<Quote> {
+    let snapshot: SnapshotResponse = serde_json::from_str(body).map_err(|e| {
+        log::error!("{}", e);
+        log::error!("{}", body);
+        e
+    })?;
+    let ticker = snapshot.ticker;
+
+    Ok(Quote {
+        symbol: ticker.ticker,
+        last: ticker.last_trade.map(|t| t.p),
+        change: ticker.todays_change,
+        volume: ticker.day.as_ref().map(|d| d.v as u64).unwrap_or(0),
+        open: ticker.day.as_ref().map(|d| d.o),
+        high: ticker.day.as_ref().map(|d| d.h),
+        low: ticker.day.as_ref().map(|d| d.l),
+        close: ticker.day.as_ref().map(|d| d.c),
+    })
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct SnapshotResponse {
+    ticker: Ticker,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Ticker {
+    ticker: String,
+    todays_change: Option<f64>,
+    day: Option<Bar>,
+    last_trade: Option<Trade>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Bar {
+    o: f64,
+    h: f64,
+    l: f64,
+    c: f64,
+    v: f64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Trade {
+    p: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_quote() {
+        let quote = parse_quote(include_str!("fixtures/snapshot.json")).unwrap();
+
+        assert_eq!("SPY", quote.symbol);
+        assert_eq!(Some(420.04), quote.last);
+        assert_eq!(Some(0.37), quote.change);
+        assert_eq!(54216630, quote.volume);
+        assert_eq!(Some(422.57), quote.open);
+        assert_eq!(Some(419.67), quote.close);
+    }
+}
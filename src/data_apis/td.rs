@@ -1,3 +1,4 @@
+pub mod auth;
 mod options;
 
 pub use options::*;
@@ -18,7 +19,7 @@ pub async fn get_option_chain(symbol: &str, force_download: bool) -> anyhow::Res
     if data_path.exists() && !force_download {
         log::info!("Fetching cached data for {}", symbol);
 
-        let json = std::fs::read_to_string(&data_path)?;
+        let json = std::fs::read_to_string(data_path)?;
         Ok(serde_json::from_str(&json)?)
     } else {
         log::info!("Downloading today's data for {}", symbol);
@@ -31,10 +32,13 @@ async fn download_data(symbol: &str, data_path: &Path) -> anyhow::Result<OptionC
     let params = format!("apikey={}&symbol={}", api_key, symbol);
     let url = format!("{}?{}", OPTION_CHAIN_URL, params);
 
-    let body = reqwest::get(url).await?.text().await?;
+    let body = match auth::token_cache() {
+        Some(token_cache) => token_cache.get(&url).await?,
+        None => reqwest::get(url).await?.text().await?,
+    };
 
     std::fs::create_dir_all(DATA_PATH)?;
-    std::fs::write(&data_path, &body)?;
+    std::fs::write(data_path, &body)?;
 
     let result: OptionChain = serde_json::from_str(&body)?;
 
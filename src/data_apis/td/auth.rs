@@ -0,0 +1,158 @@
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+const REFRESH_TOKEN_ENV: &str = "REFRESH_TOKEN";
+const TOKEN_URL: &str = "https://api.tdameritrade.com/v1/oauth2/token";
+
+// Refresh a little early so a token doesn't expire mid-request.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+static TOKEN_CACHE: Lazy<Option<TokenCache>> = Lazy::new(TokenCache::from_env);
+
+/// The shared token cache, if a refresh token is configured.
+pub fn token_cache() -> Option<&'static TokenCache> {
+    TOKEN_CACHE.as_ref()
+}
+
+#[derive(Debug)]
+struct AccessToken {
+    value: String,
+    expires_at: Instant,
+}
+
+#[derive(Debug)]
+pub struct TokenCache {
+    token_url: String,
+    client_id: String,
+    refresh_token: String,
+    client: Client,
+    token: Mutex<Option<AccessToken>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+impl TokenCache {
+    pub fn new(token_url: &str, client_id: &str, refresh_token: &str) -> Self {
+        Self {
+            token_url: token_url.to_string(),
+            client_id: client_id.to_string(),
+            refresh_token: refresh_token.to_string(),
+            client: Client::new(),
+            token: Mutex::new(None),
+        }
+    }
+
+    fn from_env() -> Option<Self> {
+        let client_id = std::env::var(super::API_KEY_ENV).ok()?;
+        let refresh_token = std::env::var(REFRESH_TOKEN_ENV).ok()?;
+        Some(Self::new(TOKEN_URL, &client_id, &refresh_token))
+    }
+
+    /// GETs `url` with the cached access token, refreshing it and retrying
+    /// once if TDA answers 401.
+    pub async fn get(&self, url: &str) -> anyhow::Result<String> {
+        let token = self.access_token().await?;
+        let response = self.client.get(url).bearer_auth(token).send().await?;
+
+        let response = if response.status() == StatusCode::UNAUTHORIZED {
+            log::info!("TDA access token rejected, refreshing");
+            let token = self.refresh().await?;
+            self.client.get(url).bearer_auth(token).send().await?
+        } else {
+            response
+        };
+
+        Ok(response.error_for_status()?.text().await?)
+    }
+
+    async fn access_token(&self) -> anyhow::Result<String> {
+        {
+            let token = self.token.lock().await;
+            if let Some(token) = token.as_ref() {
+                if token.expires_at > Instant::now() {
+                    return Ok(token.value.clone());
+                }
+            }
+        }
+
+        self.refresh().await
+    }
+
+    async fn refresh(&self) -> anyhow::Result<String> {
+        let body = self
+            .client
+            .post(&self.token_url)
+            .form(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", &self.refresh_token),
+                ("client_id", &self.client_id),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let response: TokenResponse = serde_json::from_str(&body)?;
+
+        let lifetime = Duration::from_secs(response.expires_in).saturating_sub(EXPIRY_MARGIN);
+        *self.token.lock().await = Some(AccessToken {
+            value: response.access_token.clone(),
+            expires_at: Instant::now() + lifetime,
+        });
+
+        Ok(response.access_token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+    use warp::{http::StatusCode, Filter};
+
+    #[tokio::test]
+    async fn test_refresh_after_unauthorized() {
+        let refreshes = Arc::new(AtomicU32::new(0));
+        let counter = refreshes.clone();
+        let token = warp::path("token").and(warp::post()).map(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            r#"{"access_token":"fresh","expires_in":1800}"#
+        });
+        let data = warp::path("data")
+            .and(warp::header::<String>("authorization"))
+            .map(|authorization: String| {
+                let status = if authorization == "Bearer fresh" {
+                    StatusCode::OK
+                } else {
+                    StatusCode::UNAUTHORIZED
+                };
+                warp::reply::with_status("data", status)
+            });
+        let (address, server) = warp::serve(token.or(data)).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let cache = TokenCache::new(&format!("http://{}/token", address), "id", "refresh");
+        *cache.token.lock().await = Some(AccessToken {
+            value: "revoked".to_string(),
+            expires_at: Instant::now() + Duration::from_secs(600),
+        });
+
+        let url = format!("http://{}/data", address);
+        assert_eq!("data", cache.get(&url).await.unwrap());
+        assert_eq!(1, refreshes.load(Ordering::SeqCst));
+
+        assert_eq!("data", cache.get(&url).await.unwrap());
+        assert_eq!(1, refreshes.load(Ordering::SeqCst));
+    }
+}
@@ -1,10 +1,22 @@
+use std::{fmt, time::Duration};
+
+use async_trait::async_trait;
+use chrono::{NaiveDate, Utc};
+use once_cell::sync::Lazy;
+use reqwest::{header::RETRY_AFTER, Client, RequestBuilder, Response, StatusCode};
+
+use super::{client_from_env, MarketDataProvider};
+use crate::types::{Ohlc, OhlcInterval, OptionInfo, Quote};
+
 mod get_clock;
+mod get_history;
 mod get_option_chain;
 mod get_option_expirations;
 mod get_quote;
 mod get_time_and_sales;
 
 pub use get_clock::get_clock;
+pub use get_history::get_history;
 pub use get_option_chain::get_option_chain;
 pub use get_option_expirations::get_option_expirations;
 pub use get_quote::get_quote;
@@ -12,3 +24,326 @@ pub use get_time_and_sales::get_time_and_sales;
 
 const ACCESS_TOKEN_ENV: &str = "ACCESS_TOKEN";
 const BASE_URL: &str = "https://api.tradier.com/v1";
+const BASE_URL_ENV: &str = "TRADIER_BASE_URL";
+const TIMEOUT_ENV: &str = "TRADIER_TIMEOUT_SECS";
+
+#[derive(Debug)]
+pub enum TradierError {
+    Timeout,
+    Unauthorized(String),
+    RateLimited(String),
+    Server(StatusCode, String),
+    Status(StatusCode, String),
+}
+
+impl fmt::Display for TradierError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TradierError::Timeout => write!(f, "Tradier request timed out"),
+            TradierError::Unauthorized(body) => {
+                write!(f, "Tradier rejected the access token: {}", body)
+            }
+            TradierError::RateLimited(body) => write!(f, "Tradier rate limit exceeded: {}", body),
+            TradierError::Server(status, body) => {
+                write!(f, "Tradier server error {}: {}", status, body)
+            }
+            TradierError::Status(status, body) => {
+                write!(f, "Tradier request failed with {}: {}", status, body)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TradierError {}
+
+// Shared so every call reuses pooled connections instead of paying for a new
+// TLS handshake.
+static CLIENT: Lazy<Client> = Lazy::new(|| client_from_env(TIMEOUT_ENV));
+
+const RETRY_POLICY: RetryPolicy = RetryPolicy {
+    max_attempts: 4,
+    base_delay: Duration::from_millis(500),
+    max_delay: Duration::from_secs(10),
+};
+
+struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+}
+
+impl RetryPolicy {
+    // Exponential backoff with the upper half of each delay randomized.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_delay);
+        delay.mul_f64(0.5 + rand::random::<f64>() / 2.0)
+    }
+}
+
+async fn get(url: &str) -> anyhow::Result<String> {
+    let access_token = std::env::var(ACCESS_TOKEN_ENV)?;
+
+    let request = client()
+        .get(url)
+        .header("Accept", "application/json")
+        .header("Authorization", format!("Bearer {}", access_token));
+
+    let response = send_with_retry(request, &RETRY_POLICY).await?;
+    read_body(response).await
+}
+
+// Checks the status before the body is handed to serde, so a bad token isn't
+// reported as a JSON parse error.
+async fn read_body(response: Response) -> anyhow::Result<String> {
+    let status = response.status();
+    let body = response.text().await.map_err(request_error)?;
+
+    if status.is_success() {
+        return Ok(body);
+    }
+
+    let error = match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => TradierError::Unauthorized(body),
+        StatusCode::TOO_MANY_REQUESTS => TradierError::RateLimited(body),
+        status if status.is_server_error() => TradierError::Server(status, body),
+        status => TradierError::Status(status, body),
+    };
+    log::error!("{}", error);
+    Err(error.into())
+}
+
+/// Production unless overridden, e.g. with https://sandbox.tradier.com/v1.
+fn base_url() -> String {
+    base_url_from(std::env::var(BASE_URL_ENV).ok())
+}
+
+fn base_url_from(base_url: Option<String>) -> String {
+    base_url.unwrap_or_else(|| BASE_URL.to_string())
+}
+
+fn client() -> &'static Client {
+    &CLIENT
+}
+
+fn request_error(error: reqwest::Error) -> anyhow::Error {
+    if error.is_timeout() {
+        TradierError::Timeout.into()
+    } else {
+        error.into()
+    }
+}
+
+async fn send_with_retry(
+    request: RequestBuilder,
+    policy: &RetryPolicy,
+) -> anyhow::Result<Response> {
+    let mut attempt = 1;
+    loop {
+        let response = request
+            .try_clone()
+            .ok_or_else(|| anyhow::anyhow!("Request can't be retried"))?
+            .send()
+            .await
+            .map_err(request_error)?;
+
+        let status = response.status();
+        let retryable = status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+        if !retryable || attempt >= policy.max_attempts {
+            return Ok(response);
+        }
+
+        let delay = retry_after(&response)
+            .map(|d| d.min(policy.max_delay))
+            .unwrap_or_else(|| policy.backoff(attempt));
+        log::warn!(
+            "{} returned {}, retrying in {:?}",
+            response.url().path(),
+            status,
+            delay
+        );
+
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+fn retry_after(response: &Response) -> Option<Duration> {
+    let seconds = response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+pub struct Tradier;
+
+// Daily and longer bars come from the history endpoint, looking back this far
+// when no start is given.
+fn history_lookback_days(interval: OhlcInterval) -> Option<i64> {
+    match interval {
+        OhlcInterval::Daily => Some(365),
+        OhlcInterval::Weekly => Some(5 * 365),
+        OhlcInterval::Monthly => Some(10 * 365),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl MarketDataProvider for Tradier {
+    async fn get_quote(&self, symbol: &str) -> anyhow::Result<Quote> {
+        Ok(get_quote(symbol).await?.into())
+    }
+
+    async fn get_ohlc(
+        &self,
+        symbol: &str,
+        interval: OhlcInterval,
+        start: Option<NaiveDate>,
+        end: Option<NaiveDate>,
+    ) -> anyhow::Result<Vec<Ohlc>> {
+        if let Some(lookback_days) = history_lookback_days(interval) {
+            let end = end.unwrap_or_else(|| Utc::now().date_naive());
+            let start = start.unwrap_or(end - chrono::Duration::days(lookback_days));
+            return get_history(symbol, &start.to_string(), &end.to_string(), interval).await;
+        }
+
+        let ohlc = get_time_and_sales(symbol, interval, start, end).await?;
+        Ok(ohlc.into_iter().map(|ts| (interval, ts).into()).collect())
+    }
+
+    async fn get_option_chain(&self, symbol: &str) -> anyhow::Result<Vec<OptionInfo>> {
+        get_option_chain(symbol).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+    use warp::Filter;
+
+    const TEST_POLICY: RetryPolicy = RetryPolicy {
+        max_attempts: 4,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(10),
+    };
+
+    // Serves `failures` 503s before answering 200, returning the url and a
+    // count of requests received.
+    fn flaky_server(failures: u32) -> (String, Arc<AtomicU32>) {
+        let requests = Arc::new(AtomicU32::new(0));
+        let counter = requests.clone();
+        let route = warp::any().map(move || {
+            let status = if counter.fetch_add(1, Ordering::SeqCst) < failures {
+                warp::http::StatusCode::SERVICE_UNAVAILABLE
+            } else {
+                warp::http::StatusCode::OK
+            };
+            warp::reply::with_header(warp::reply::with_status("{}", status), "Retry-After", "0")
+        });
+        let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        (format!("http://{}", address), requests)
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_success() {
+        let (url, requests) = flaky_server(2);
+
+        let request = reqwest::Client::new().get(&url);
+        let response = send_with_retry(request, &TEST_POLICY).await.unwrap();
+
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(3, requests.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let (url, requests) = flaky_server(10);
+
+        let request = reqwest::Client::new().get(&url);
+        let response = send_with_retry(request, &TEST_POLICY).await.unwrap();
+
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, response.status());
+        assert_eq!(4, requests.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let route = warp::any().and_then(|| async {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            Ok::<_, std::convert::Infallible>("{}")
+        });
+        let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let client = crate::data_apis::build_client(Duration::from_millis(50));
+        let request = client.get(format!("http://{}", address));
+        let error = send_with_retry(request, &TEST_POLICY).await.unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<TradierError>(),
+            Some(TradierError::Timeout)
+        ));
+    }
+
+    async fn error_for(status: u16, body: &'static str) -> TradierError {
+        let route = warp::any().map(move || {
+            warp::reply::with_status(body, warp::http::StatusCode::from_u16(status).unwrap())
+        });
+        let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let response = reqwest::get(format!("http://{}", address)).await.unwrap();
+        read_body(response)
+            .await
+            .unwrap_err()
+            .downcast::<TradierError>()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized() {
+        match error_for(401, "Invalid Access Token").await {
+            TradierError::Unauthorized(body) => assert_eq!("Invalid Access Token", body),
+            error => panic!("Unexpected error: {:?}", error),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited() {
+        match error_for(429, "Quota Violation").await {
+            TradierError::RateLimited(body) => assert_eq!("Quota Violation", body),
+            error => panic!("Unexpected error: {:?}", error),
+        }
+    }
+
+    #[test]
+    fn test_base_url_override() {
+        assert_eq!(
+            "https://sandbox.tradier.com/v1",
+            base_url_from(Some("https://sandbox.tradier.com/v1".to_string()))
+        );
+        assert_eq!(BASE_URL, base_url_from(None));
+    }
+
+    #[test]
+    fn test_backoff() {
+        for attempt in 1..=3 {
+            let delay = RETRY_POLICY.backoff(attempt);
+            let full = RETRY_POLICY.base_delay * 2u32.pow(attempt - 1);
+            assert!(delay >= full / 2 && delay <= full);
+        }
+        assert!(RETRY_POLICY.backoff(20) <= RETRY_POLICY.max_delay);
+    }
+}
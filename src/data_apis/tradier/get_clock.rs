@@ -4,18 +4,9 @@ use crate::types;
 use serde::{Deserialize, Serialize};
 
 pub async fn get_clock() -> anyhow::Result<types::Clock> {
-    let access_token = std::env::var(super::ACCESS_TOKEN_ENV)?;
-    let url = format!("{}/markets/clock", super::BASE_URL);
+    let url = format!("{}/markets/clock", super::base_url());
 
-    let client = reqwest::Client::new();
-    let body = client
-        .get(url)
-        .header("Accept", "application/json")
-        .header("Authorization", format!("Bearer {}", access_token))
-        .send()
-        .await?
-        .text()
-        .await?;
+    let body = super::get(&url).await?;
 
     let clock: ClockResponse = serde_json::from_str(&body).map_err(|e| {
         log::error!("{}", e);
@@ -23,7 +14,7 @@ pub async fn get_clock() -> anyhow::Result<types::Clock> {
         e
     })?;
 
-    Ok(clock.clock.try_into()?)
+    clock.clock.try_into()
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -0,0 +1,125 @@
+use serde::{de::IgnoredAny, Deserialize};
+
+use crate::types::{Ohlc, OhlcInterval};
+
+pub async fn get_history(
+    symbol: &str,
+    start: &str,
+    end: &str,
+    interval: OhlcInterval,
+) -> anyhow::Result<Vec<Ohlc>> {
+    match interval {
+        OhlcInterval::Daily | OhlcInterval::Weekly | OhlcInterval::Monthly => {}
+        _ => anyhow::bail!("History is only available in daily, weekly or monthly bars"),
+    }
+
+    let params = format!(
+        "symbol={}&interval={}&start={}&end={}",
+        symbol, interval, start, end
+    );
+    let url = format!("{}/markets/history?{}", super::base_url(), params);
+
+    let body = super::get(&url).await?;
+
+    parse_history(&body, interval)
+}
+
+fn parse_history(body: &str, interval: OhlcInterval) -> anyhow::Result<Vec<Ohlc>> {
+    let history: HistoryResponse = serde_json::from_str(body).map_err(|e| {
+        log::error!("{}", e);
+        log::error!("{}", body);
+        e
+    })?;
+
+    // Tradier sends a bare object for a single bar and "null" for none.
+    let days = match history.history {
+        History::Bars(HistoryInner {
+            day: Some(OneOrMany::Many(days)),
+        }) => days,
+        History::Bars(HistoryInner {
+            day: Some(OneOrMany::One(day)),
+        }) => vec![day],
+        _ => Vec::new(),
+    };
+
+    Ok(days
+        .into_iter()
+        .map(|day| Ohlc {
+            interval,
+            time: day.date,
+            price: day.close,
+            open: day.open,
+            high: day.high,
+            low: day.low,
+            close: day.close,
+            volume: day.volume,
+            vwap: None,
+        })
+        .collect())
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct HistoryResponse {
+    history: History,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+enum History {
+    Bars(HistoryInner),
+    Empty(IgnoredAny),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct HistoryInner {
+    day: Option<OneOrMany>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(HistoryDay),
+    Many(Vec<HistoryDay>),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct HistoryDay {
+    date: String,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_history() {
+        let history =
+            parse_history(include_str!("fixtures/history.json"), OhlcInterval::Daily).unwrap();
+
+        assert_eq!(3, history.len());
+        assert_eq!("2021-06-01", history[0].time);
+        assert_eq!(422.57, history[0].open);
+        assert_eq!(418.77, history[2].close);
+        assert_eq!(58138789, history[2].volume);
+        assert_eq!(OhlcInterval::Daily, history[2].interval);
+    }
+
+    #[test]
+    fn test_parse_history_single_and_empty() {
+        let single = r#"{"history":{"day":{"date":"2021-06-01","open":1.0,"high":2.0,"low":0.5,"close":1.5,"volume":10}}}"#;
+        assert_eq!(
+            1,
+            parse_history(single, OhlcInterval::Weekly).unwrap().len()
+        );
+
+        let empty = r#"{"history":"null"}"#;
+        assert!(parse_history(empty, OhlcInterval::Monthly)
+            .unwrap()
+            .is_empty());
+    }
+}
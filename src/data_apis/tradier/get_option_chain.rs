@@ -6,8 +6,6 @@ use serde::{Deserialize, Serialize};
 use crate::{math::bs, types};
 
 pub async fn get_option_chain(symbol: &str) -> anyhow::Result<Vec<types::OptionInfo>> {
-    let access_token = std::env::var(super::ACCESS_TOKEN_ENV)?;
-
     let expirations = super::get_option_expirations(symbol).await?;
     let quote = super::get_quote(symbol).await?;
     let current_price = quote.last.unwrap_or(0.0);
@@ -16,17 +14,9 @@ pub async fn get_option_chain(symbol: &str) -> anyhow::Result<Vec<types::OptionI
 
     for expiration in expirations {
         let params = format!("symbol={}&expiration={}&greeks=true", symbol, expiration);
-        let url = format!("{}/markets/options/chains?{}", super::BASE_URL, params);
-
-        let client = reqwest::Client::new();
-        let body = client
-            .get(url)
-            .header("Accept", "application/json")
-            .header("Authorization", format!("Bearer {}", access_token))
-            .send()
-            .await?
-            .text()
-            .await?;
+        let url = format!("{}/markets/options/chains?{}", super::base_url(), params);
+
+        let body = super::get(&url).await?;
 
         let response = serde_json::from_str::<OptionChainResponse>(&body).map_err(|e| {
             log::error!("{}", e);
@@ -115,57 +105,43 @@ impl OptionInfo {
 
         let greeks = match &self.greeks {
             Some(g) => {
-                let expiration_time = 180.0;
-                let current_time = 0.0;
-
-                let delta = match option_type {
-                    types::OptionType::Call => bs::call_delta,
-                    types::OptionType::Put => bs::put_delta,
-                };
+                let tau = 180.0;
+                let risk_free_rate = bs::DEFAULT_RISK_FREE_RATE;
+                let dividend_yield = bs::DEFAULT_DIVIDEND_YIELD;
+                let is_call = option_type == types::OptionType::Call;
+
+                let bs_greeks = bs::Greeks::compute(
+                    g.mid_iv,
+                    tau,
+                    current_price,
+                    self.strike,
+                    is_call,
+                    risk_free_rate,
+                    dividend_yield,
+                );
 
                 Some(types::Greeks {
-                    delta: delta(
-                        g.mid_iv,
-                        expiration_time,
-                        current_time,
-                        current_price,
-                        self.strike,
-                    ),
-                    gamma: bs::gamma(
-                        g.mid_iv,
-                        expiration_time,
-                        current_time,
-                        current_price,
-                        self.strike,
-                    ),
-                    theta: bs::theta(
-                        g.mid_iv,
-                        expiration_time,
-                        current_time,
-                        current_price,
-                        self.strike,
-                    ),
-                    vega: bs::vega(
-                        g.mid_iv,
-                        expiration_time,
-                        current_time,
-                        current_price,
-                        self.strike,
-                    ),
+                    delta: bs_greeks.delta,
+                    gamma: bs_greeks.gamma,
+                    theta: bs_greeks.theta / 365.0,
+                    vega: bs_greeks.vega / 100.0,
                     rho: g.rho,
                     vanna: bs::vanna(
                         g.mid_iv,
-                        expiration_time,
-                        current_time,
+                        tau,
                         current_price,
                         self.strike,
-                    ),
+                        risk_free_rate,
+                        dividend_yield,
+                    ) / 100.0,
                     charm: bs::charm(
                         g.mid_iv,
-                        expiration_time,
-                        current_time,
+                        tau,
                         current_price,
                         self.strike,
+                        is_call,
+                        risk_free_rate,
+                        dividend_yield,
                     ),
                 })
             }
@@ -186,6 +162,8 @@ impl OptionInfo {
             high: self.high,
             low: self.low,
             close: self.close,
+            bid: self.bid,
+            ask: self.ask,
             greeks,
             bid_iv: self.greeks.as_ref().map(|g| g.bid_iv),
             mid_iv: self.greeks.as_ref().map(|g| g.mid_iv),
@@ -1,29 +1,30 @@
 use serde::Deserialize;
 
+use crate::data_apis::SymbolError;
+
 pub async fn get_option_expirations(symbol: &str) -> anyhow::Result<Vec<String>> {
-    let access_token = std::env::var(super::ACCESS_TOKEN_ENV)?;
     let params = format!("symbol={}&includeAllRoots=true", symbol);
-    let url = format!("{}/markets/options/expirations?{}", super::BASE_URL, params);
-
-    let client = reqwest::Client::new();
-    let body = client
-        .get(url)
-        .header("Accept", "application/json")
-        .header("Authorization", format!("Bearer {}", access_token))
-        .send()
-        .await?
-        .text()
-        .await?;
-
-    let expirations: ExpirationResponse = serde_json::from_str(&body).map_err(|e| {
+    let url = format!(
+        "{}/markets/options/expirations?{}",
+        super::base_url(),
+        params
+    );
+
+    let body = super::get(&url).await?;
+
+    parse_expirations(symbol, &body)
+}
+
+fn parse_expirations(symbol: &str, body: &str) -> anyhow::Result<Vec<String>> {
+    let expirations: ExpirationResponse = serde_json::from_str(body).map_err(|e| {
         log::error!("{}", e);
-        log::error!("{}", &body);
+        log::error!("{}", body);
         e
     })?;
 
     Ok(expirations
         .expirations
-        .ok_or_else(|| anyhow::anyhow!("No expirations"))?
+        .ok_or_else(|| SymbolError::NoOptions(symbol.to_uppercase()))?
         .date)
 }
 
@@ -36,3 +37,23 @@ struct ExpirationResponse {
 struct ExpirationResponseInner {
     date: Vec<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_expirations() {
+        let expirations =
+            parse_expirations("SPY", include_str!("fixtures/expirations.json")).unwrap();
+        assert_eq!(6, expirations.len());
+        assert_eq!("2021-06-04", expirations[0]);
+        assert_eq!("2021-07-16", expirations[5]);
+
+        let error = parse_expirations("xyz", r#"{"expirations":null}"#).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<SymbolError>(),
+            Some(SymbolError::NoOptions(symbol)) if symbol == "XYZ"
+        ));
+    }
+}
@@ -1,28 +1,27 @@
-use crate::types;
+use crate::{data_apis::SymbolError, types};
 use serde::{Deserialize, Serialize};
 
 pub async fn get_quote(symbol: &str) -> anyhow::Result<Quote> {
-    let access_token = std::env::var(super::ACCESS_TOKEN_ENV)?;
     let params = format!("symbols={}", symbol);
-    let url = format!("{}/markets/quotes?{}", super::BASE_URL, params);
+    let url = format!("{}/markets/quotes?{}", super::base_url(), params);
 
-    let client = reqwest::Client::new();
-    let body = client
-        .get(url)
-        .header("Accept", "application/json")
-        .header("Authorization", format!("Bearer {}", access_token))
-        .send()
-        .await?
-        .text()
-        .await?;
+    let body = super::get(&url).await?;
 
-    let quotes: QuoteResponse = serde_json::from_str(&body).map_err(|e| {
+    parse_quote(symbol, &body)
+}
+
+fn parse_quote(symbol: &str, body: &str) -> anyhow::Result<Quote> {
+    let quotes: QuoteResponse = serde_json::from_str(body).map_err(|e| {
         log::error!("{}", e);
-        log::error!("{}", &body);
+        log::error!("{}", body);
         e
     })?;
 
-    Ok(quotes.quotes.quote)
+    // Unknown symbols come back as `unmatched_symbols` with no quote.
+    quotes
+        .quotes
+        .quote
+        .ok_or_else(|| SymbolError::Unknown(symbol.to_uppercase()).into())
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -65,7 +64,7 @@ struct QuoteResponse {
 
 #[derive(Clone, Debug, Deserialize)]
 struct QuoteResponseInner {
-    quote: Quote,
+    quote: Option<Quote>,
 }
 
 impl From<Quote> for types::Quote {
@@ -82,3 +81,20 @@ impl From<Quote> for types::Quote {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_unmatched_symbol() {
+        let body = r#"{"quotes":{"unmatched_symbols":{"symbol":"XYZXYZ"}}}"#;
+
+        let error = parse_quote("xyzxyz", body).unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<SymbolError>(),
+            Some(SymbolError::Unknown(symbol)) if symbol == "XYZXYZ"
+        ));
+    }
+}
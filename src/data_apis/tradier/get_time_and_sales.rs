@@ -1,36 +1,20 @@
-use chrono::{Datelike, Duration, Utc};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc, Weekday};
+use chrono_tz::America::New_York;
 use serde::{Deserialize, Serialize};
+use std::sync::Once;
 
 use crate::types::{self as graphql, OhlcInterval};
 
 pub async fn get_time_and_sales(
     symbol: &str,
     interval: OhlcInterval,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
 ) -> anyhow::Result<Vec<TimeAndSales>> {
-    let now = Utc::now() - Duration::hours(4);
+    let tradier_interval = time_and_sales_interval(interval)?;
+    let url = time_and_sales_url(&super::base_url(), symbol, tradier_interval, start, end);
 
-    let lookback_days = match now.weekday() {
-        chrono::Weekday::Sun => 5,
-        chrono::Weekday::Sat => 4,
-        _ => 3,
-    };
-    let start = (now - Duration::days(lookback_days))
-        .format("%Y-%m-%d %H:%M")
-        .to_string();
-
-    let access_token = std::env::var(super::ACCESS_TOKEN_ENV)?;
-    let params = format!("symbol={}&interval={}&start={}", symbol, interval, start);
-    let url = format!("{}/markets/timesales?{}", super::BASE_URL, params);
-
-    let client = reqwest::Client::new();
-    let body = client
-        .get(url)
-        .header("Accept", "application/json")
-        .header("Authorization", format!("Bearer {}", access_token))
-        .send()
-        .await?
-        .text()
-        .await?;
+    let body = super::get(&url).await?;
 
     let time_and_sales: TimeAndSalesResponse = serde_json::from_str(&body).map_err(|e| {
         log::error!("{}", e);
@@ -38,7 +22,239 @@ pub async fn get_time_and_sales(
         e
     })?;
 
-    Ok(time_and_sales.series.unwrap_or_default().data)
+    let mut bars = time_and_sales.series.unwrap_or_default().data;
+    fill_missing_vwap(&mut bars);
+    Ok(match interval {
+        OhlcInterval::Hourly => hourly_bars(bars),
+        _ => bars,
+    })
+}
+
+// Tradier has no hourly time and sales, so hourly bars are built from 15
+// minute ones. Daily and longer bars come from the history endpoint.
+fn time_and_sales_interval(interval: OhlcInterval) -> anyhow::Result<&'static str> {
+    Ok(match interval {
+        OhlcInterval::Tick => "tick",
+        OhlcInterval::OneMinute => "1min",
+        OhlcInterval::FiveMinute => "5min",
+        OhlcInterval::FifteenMinute | OhlcInterval::Hourly => "15min",
+        OhlcInterval::Daily | OhlcInterval::Weekly | OhlcInterval::Monthly => {
+            anyhow::bail!("Time and sales don't support {} bars", interval)
+        }
+    })
+}
+
+// Bars without an upstream vwap get a cumulative one over the series so far,
+// keeping the line continuous.
+fn fill_missing_vwap(bars: &mut [TimeAndSales]) {
+    let mut price_volume = 0.0;
+    let mut volume = 0.0;
+
+    for bar in bars {
+        price_volume += bar.price * bar.volume as f64;
+        volume += bar.volume as f64;
+
+        if bar.vwap.is_none() && volume > 0.0 {
+            bar.vwap = Some(price_volume / volume);
+        }
+    }
+}
+
+// Merges consecutive bars that start in the same clock hour. Bar times are
+// in exchange time, whose UTC offset is a whole number of hours, so the
+// timestamp can be floored directly.
+fn hourly_bars(bars: Vec<TimeAndSales>) -> Vec<TimeAndSales> {
+    let mut hourly: Vec<TimeAndSales> = vec![];
+    let mut vwap_volume = 0.0;
+
+    for bar in bars {
+        let hour_timestamp = bar.timestamp - bar.timestamp % 3600;
+        match hourly.last_mut() {
+            Some(current) if current.timestamp == hour_timestamp => {
+                current.vwap = match (current.vwap, bar.vwap) {
+                    (Some(current_vwap), Some(vwap)) => {
+                        let volume = vwap_volume + bar.volume as f64;
+                        let merged = if volume > 0.0 {
+                            (current_vwap * vwap_volume + vwap * bar.volume as f64) / volume
+                        } else {
+                            vwap
+                        };
+                        vwap_volume = volume;
+                        Some(merged)
+                    }
+                    _ => None,
+                };
+                current.price = bar.price;
+                current.high = current.high.max(bar.high);
+                current.low = current.low.min(bar.low);
+                current.close = bar.close;
+                current.volume += bar.volume;
+            }
+            _ => {
+                vwap_volume = bar.volume as f64;
+                hourly.push(TimeAndSales {
+                    time: format!("{}:00:00", &bar.time[..bar.time.len().min(13)]),
+                    timestamp: hour_timestamp,
+                    ..bar
+                });
+            }
+        }
+    }
+
+    hourly
+}
+
+// Explicit dates cover whole days in exchange time.
+fn time_and_sales_url(
+    base_url: &str,
+    symbol: &str,
+    interval: &str,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+) -> String {
+    let start = match start {
+        Some(start) => format!("{} 00:00", start),
+        None => lookback_start(Utc::now(), &market_holidays()),
+    };
+    let mut params = format!("symbol={}&interval={}&start={}", symbol, interval, start);
+    if let Some(end) = end {
+        params.push_str(&format!("&end={} 23:59", end));
+    }
+
+    format!("{}/markets/timesales?{}", base_url, params)
+}
+
+const MARKET_HOLIDAYS_ENV: &str = "MARKET_HOLIDAYS";
+
+// Full-day NYSE closures. More can be added at runtime as a comma-separated
+// list of dates in MARKET_HOLIDAYS.
+const MARKET_HOLIDAYS: &[&str] = &[
+    "2021-01-01",
+    "2021-01-18",
+    "2021-02-15",
+    "2021-04-02",
+    "2021-05-31",
+    "2021-07-05",
+    "2021-09-06",
+    "2021-11-25",
+    "2021-12-24",
+    "2022-01-17",
+    "2022-02-21",
+    "2022-04-15",
+    "2022-05-30",
+    "2022-06-20",
+    "2022-07-04",
+    "2022-09-05",
+    "2022-11-24",
+    "2022-12-26",
+    "2023-01-02",
+    "2023-01-16",
+    "2023-02-20",
+    "2023-04-07",
+    "2023-05-29",
+    "2023-06-19",
+    "2023-07-04",
+    "2023-09-04",
+    "2023-11-23",
+    "2023-12-25",
+    "2024-01-01",
+    "2024-01-15",
+    "2024-02-19",
+    "2024-03-29",
+    "2024-05-27",
+    "2024-06-19",
+    "2024-07-04",
+    "2024-09-02",
+    "2024-11-28",
+    "2024-12-25",
+    "2025-01-01",
+    "2025-01-09",
+    "2025-01-20",
+    "2025-02-17",
+    "2025-04-18",
+    "2025-05-26",
+    "2025-06-19",
+    "2025-07-04",
+    "2025-09-01",
+    "2025-11-27",
+    "2025-12-25",
+    "2026-01-01",
+    "2026-01-19",
+    "2026-02-16",
+    "2026-04-03",
+    "2026-05-25",
+    "2026-06-19",
+    "2026-07-03",
+    "2026-09-07",
+    "2026-11-26",
+    "2026-12-25",
+    "2027-01-01",
+    "2027-01-18",
+    "2027-02-15",
+    "2027-03-26",
+    "2027-05-31",
+    "2027-06-18",
+    "2027-07-05",
+    "2027-09-06",
+    "2027-11-25",
+    "2027-12-24",
+];
+
+static HOLIDAYS_OUTDATED_WARNING: Once = Once::new();
+
+fn market_holidays() -> Vec<NaiveDate> {
+    if holidays_outdated(Utc::now().with_timezone(&New_York).year()) {
+        HOLIDAYS_OUTDATED_WARNING.call_once(|| {
+            log::warn!(
+                "Built-in market holidays end before this year; set {}",
+                MARKET_HOLIDAYS_ENV
+            )
+        });
+    }
+
+    let configured = std::env::var(MARKET_HOLIDAYS_ENV).unwrap_or_default();
+    MARKET_HOLIDAYS
+        .iter()
+        .copied()
+        .chain(configured.split(','))
+        .filter_map(|date| NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok())
+        .collect()
+}
+
+fn holidays_outdated(year: i32) -> bool {
+    MARKET_HOLIDAYS
+        .last()
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .is_none_or(|last| year > last.year())
+}
+
+// Tradier expects the start in exchange time. Days are subtracted on the wall
+// clock so the hour doesn't shift when the window spans a DST change.
+fn lookback_start(now: DateTime<Utc>, holidays: &[NaiveDate]) -> String {
+    let now = now.with_timezone(&New_York).naive_local();
+
+    let lookback_days = match now.weekday() {
+        Weekday::Sun => 5,
+        Weekday::Sat => 4,
+        _ => 3,
+    };
+    let mut start = now - Duration::days(lookback_days);
+
+    // Every holiday inside the window costs a session, so reach back one
+    // more trading day for each.
+    let mut missing_sessions = holidays
+        .iter()
+        .filter(|h| **h >= start.date() && **h < now.date())
+        .count();
+    while missing_sessions > 0 {
+        start -= Duration::days(1);
+        let weekend = matches!(start.weekday(), Weekday::Sat | Weekday::Sun);
+        if !weekend && !holidays.contains(&start.date()) {
+            missing_sessions -= 1;
+        }
+    }
+
+    start.format("%Y-%m-%d %H:%M").to_string()
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -79,3 +295,155 @@ impl From<(graphql::OhlcInterval, TimeAndSales)> for graphql::Ohlc {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn utc(y: i32, m: u32, d: u32, h: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_lookback_start_across_dst() {
+        // Friday before DST starts, EST is UTC-5.
+        assert_eq!(
+            "2021-03-09 10:00",
+            lookback_start(utc(2021, 3, 12, 15), &[])
+        );
+        // Monday after DST starts, EDT is UTC-4.
+        assert_eq!(
+            "2021-03-12 11:00",
+            lookback_start(utc(2021, 3, 15, 15), &[])
+        );
+        // Monday after DST ends, back to EST.
+        assert_eq!(
+            "2021-11-05 10:00",
+            lookback_start(utc(2021, 11, 8, 15), &[])
+        );
+    }
+
+    #[test]
+    fn test_lookback_start_after_holiday() {
+        let holidays = market_holidays();
+
+        // Tuesday after Memorial Day reaches back to Friday's session.
+        assert_eq!(
+            "2021-05-28 10:00",
+            lookback_start(utc(2021, 6, 1, 14), &holidays)
+        );
+        // The Friday after Thanksgiving gets an extra day on top of the usual three.
+        assert_eq!(
+            "2021-11-22 10:00",
+            lookback_start(utc(2021, 11, 26, 15), &holidays)
+        );
+        // Without the calendar the window starts on the holiday weekend.
+        assert_eq!("2021-05-29 10:00", lookback_start(utc(2021, 6, 1, 14), &[]));
+    }
+
+    #[test]
+    fn test_holidays_outdated() {
+        assert!(!holidays_outdated(2027));
+        assert!(holidays_outdated(2028));
+    }
+
+    #[test]
+    fn test_lookback_start_weekend() {
+        // Sunday afternoon in New York, but already Monday in UTC.
+        assert_eq!("2021-06-01 20:00", lookback_start(utc(2021, 6, 7, 0), &[]));
+    }
+
+    #[test]
+    fn test_time_and_sales_interval() {
+        use OhlcInterval::*;
+
+        assert_eq!("tick", time_and_sales_interval(Tick).unwrap());
+        assert_eq!("1min", time_and_sales_interval(OneMinute).unwrap());
+        assert_eq!("5min", time_and_sales_interval(FiveMinute).unwrap());
+        assert_eq!("15min", time_and_sales_interval(FifteenMinute).unwrap());
+        assert_eq!("15min", time_and_sales_interval(Hourly).unwrap());
+        for interval in &[Daily, Weekly, Monthly] {
+            assert!(time_and_sales_interval(*interval).is_err());
+        }
+    }
+
+    fn bar(time: &str, timestamp: u64, close: f64, volume: u64, vwap: f64) -> TimeAndSales {
+        TimeAndSales {
+            time: time.to_string(),
+            timestamp,
+            price: close,
+            open: close - 1.0,
+            high: close + 1.0,
+            low: close - 2.0,
+            close,
+            volume,
+            vwap: Some(vwap),
+        }
+    }
+
+    #[test]
+    fn test_fill_missing_vwap() {
+        let mut bars = vec![
+            bar("2021-06-01T09:30:00", 0, 100.0, 0, 0.0),
+            bar("2021-06-01T09:35:00", 300, 100.0, 100, 0.0),
+            bar("2021-06-01T09:40:00", 600, 104.0, 300, 101.5),
+            bar("2021-06-01T09:45:00", 900, 106.0, 400, 0.0),
+        ];
+        bars[0].vwap = None;
+        bars[1].vwap = None;
+        bars[3].vwap = None;
+
+        fill_missing_vwap(&mut bars);
+
+        // Nothing has traded yet, so there's no price to weight.
+        assert_eq!(None, bars[0].vwap);
+        assert_eq!(Some(100.0), bars[1].vwap);
+        assert_eq!(Some(101.5), bars[2].vwap);
+        assert_eq!(Some(104.5), bars[3].vwap);
+    }
+
+    #[test]
+    fn test_hourly_bars() {
+        // 2021-06-01 09:30 EDT is 13:30 UTC.
+        let nine_thirty = 1_622_554_200;
+        let bars = vec![
+            bar("2021-06-01T09:30:00", nine_thirty, 100.0, 100, 99.0),
+            bar("2021-06-01T09:45:00", nine_thirty + 900, 102.0, 300, 103.0),
+            bar("2021-06-01T10:00:00", nine_thirty + 1800, 101.0, 50, 101.0),
+        ];
+
+        let hourly = hourly_bars(bars);
+
+        assert_eq!(2, hourly.len());
+        assert_eq!("2021-06-01T09:00:00", hourly[0].time);
+        assert_eq!(nine_thirty - 1800, hourly[0].timestamp);
+        assert_eq!(99.0, hourly[0].open);
+        assert_eq!(103.0, hourly[0].high);
+        assert_eq!(98.0, hourly[0].low);
+        assert_eq!(102.0, hourly[0].close);
+        assert_eq!(400, hourly[0].volume);
+        assert_eq!(Some(102.0), hourly[0].vwap);
+        assert_eq!("2021-06-01T10:00:00", hourly[1].time);
+        assert_eq!(50, hourly[1].volume);
+    }
+
+    #[test]
+    fn test_time_and_sales_url_with_dates() {
+        let url = time_and_sales_url(
+            "http://localhost",
+            "SPY",
+            "5min",
+            NaiveDate::from_ymd_opt(2021, 6, 1),
+            NaiveDate::from_ymd_opt(2021, 6, 4),
+        );
+        assert_eq!(
+            "http://localhost/markets/timesales?symbol=SPY&interval=5min&start=2021-06-01 00:00&end=2021-06-04 23:59",
+            url
+        );
+
+        let url = time_and_sales_url("http://localhost", "SPY", "5min", None, None);
+        assert!(url.contains("&start="));
+        assert!(!url.contains("&end="));
+    }
+}
@@ -0,0 +1,55 @@
+mod get_chart;
+
+pub use get_chart::{get_ohlc, get_quote};
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use once_cell::sync::Lazy;
+use reqwest::Client;
+
+use super::{client_from_env, MarketDataProvider};
+use crate::types::{Ohlc, OhlcInterval, OptionInfo, Quote};
+
+const BASE_URL: &str = "https://query1.finance.yahoo.com";
+// Yahoo rejects requests without a browser-like user agent.
+const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:89.0) Gecko/20100101 Firefox/89.0";
+const TIMEOUT_ENV: &str = "YAHOO_TIMEOUT_SECS";
+
+static CLIENT: Lazy<Client> = Lazy::new(|| client_from_env(TIMEOUT_ENV));
+
+async fn get(url: &str) -> anyhow::Result<String> {
+    let body = CLIENT
+        .get(url)
+        .header("Accept", "application/json")
+        .header("User-Agent", USER_AGENT)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    Ok(body)
+}
+
+pub struct Yahoo;
+
+#[async_trait]
+impl MarketDataProvider for Yahoo {
+    async fn get_quote(&self, symbol: &str) -> anyhow::Result<Quote> {
+        get_quote(symbol).await
+    }
+
+    async fn get_ohlc(
+        &self,
+        symbol: &str,
+        interval: OhlcInterval,
+        start: Option<NaiveDate>,
+        end: Option<NaiveDate>,
+    ) -> anyhow::Result<Vec<Ohlc>> {
+        get_ohlc(symbol, interval, start, end).await
+    }
+
+    async fn get_option_chain(&self, _symbol: &str) -> anyhow::Result<Vec<OptionInfo>> {
+        anyhow::bail!("Yahoo doesn't provide option chains")
+    }
+}
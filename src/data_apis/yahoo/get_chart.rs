@@ -0,0 +1,286 @@
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::America::New_York;
+use serde::Deserialize;
+
+use crate::types::{Ohlc, OhlcInterval, Quote};
+
+pub async fn get_quote(symbol: &str) -> anyhow::Result<Quote> {
+    let body = super::get(&chart_url(symbol, "1d", "range=1d")).await?;
+    parse_quote(&body)
+}
+
+pub async fn get_ohlc(
+    symbol: &str,
+    interval: OhlcInterval,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+) -> anyhow::Result<Vec<Ohlc>> {
+    let (yahoo_interval, range, lookback_days) = match interval {
+        OhlcInterval::Tick => anyhow::bail!("Yahoo charts don't support tick bars"),
+        OhlcInterval::OneMinute => ("1m", "5d", 5),
+        OhlcInterval::FiveMinute => ("5m", "5d", 5),
+        OhlcInterval::FifteenMinute => ("15m", "5d", 5),
+        OhlcInterval::Hourly => ("60m", "1mo", 30),
+        OhlcInterval::Daily => ("1d", "1y", 365),
+        OhlcInterval::Weekly => ("1wk", "5y", 5 * 365),
+        OhlcInterval::Monthly => ("1mo", "10y", 10 * 365),
+    };
+
+    let window = chart_window(range, lookback_days, start, end, Utc::now().timestamp())?;
+    let body = super::get(&chart_url(symbol, yahoo_interval, &window)).await?;
+    parse_ohlc(&body, interval)
+}
+
+// Yahoo takes either a named range back from now or explicit unix times.
+fn chart_window(
+    range: &str,
+    lookback_days: i64,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+    now: i64,
+) -> anyhow::Result<String> {
+    if start.is_none() && end.is_none() {
+        return Ok(format!("range={}", range));
+    }
+
+    let period2 = match end {
+        Some(end) => start_of_day(end + Duration::days(1))?,
+        None => now,
+    };
+    let period1 = match start {
+        Some(start) => start_of_day(start)?,
+        None => period2 - Duration::days(lookback_days).num_seconds(),
+    };
+
+    Ok(format!("period1={}&period2={}", period1, period2))
+}
+
+fn chart_url(symbol: &str, interval: &str, window: &str) -> String {
+    format!(
+        "{}/v8/finance/chart/{}?interval={}&{}",
+        super::BASE_URL,
+        symbol.to_uppercase(),
+        interval,
+        window
+    )
+}
+
+// Unix time of midnight in exchange time.
+fn start_of_day(date: NaiveDate) -> anyhow::Result<i64> {
+    let midnight = date
+        .and_hms_opt(0, 0, 0)
+        .and_then(|midnight| New_York.from_local_datetime(&midnight).earliest())
+        .ok_or_else(|| anyhow::anyhow!("Invalid date: {}", date))?;
+    Ok(midnight.timestamp())
+}
+
+fn parse_chart(body: &str) -> anyhow::Result<ChartResult> {
+    let response: ChartResponse = serde_json::from_str(body).map_err(|e| {
+        log::error!("{}", e);
+        log::error!("{}", body);
+        e
+    })?;
+
+    if let Some(error) = response.chart.error {
+        anyhow::bail!("Yahoo chart error: {}: {}", error.code, error.description);
+    }
+
+    response
+        .chart
+        .result
+        .and_then(|results| results.into_iter().next())
+        .ok_or_else(|| anyhow::anyhow!("Yahoo chart response has no results"))
+}
+
+fn parse_quote(body: &str) -> anyhow::Result<Quote> {
+    let chart = parse_chart(body)?;
+    let last = chart.meta.regular_market_price;
+    let bar = chart.bars().last().copied();
+
+    Ok(Quote {
+        symbol: chart.meta.symbol.clone(),
+        last,
+        change: last
+            .zip(chart.meta.chart_previous_close)
+            .map(|(last, previous)| last - previous),
+        volume: bar.map(|bar| bar.volume).unwrap_or(0),
+        open: bar.map(|bar| bar.open),
+        high: bar.map(|bar| bar.high),
+        low: bar.map(|bar| bar.low),
+        close: bar.map(|bar| bar.close),
+    })
+}
+
+fn parse_ohlc(body: &str, interval: OhlcInterval) -> anyhow::Result<Vec<Ohlc>> {
+    let chart = parse_chart(body)?;
+
+    chart
+        .bars()
+        .into_iter()
+        .map(|bar| {
+            let time = New_York
+                .timestamp_opt(bar.timestamp, 0)
+                .single()
+                .ok_or_else(|| anyhow::anyhow!("Invalid timestamp: {}", bar.timestamp))?;
+
+            Ok(Ohlc {
+                interval,
+                time: time.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                price: bar.close,
+                open: bar.open,
+                high: bar.high,
+                low: bar.low,
+                close: bar.close,
+                volume: bar.volume,
+                vwap: None,
+            })
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ChartResponse {
+    chart: Chart,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Chart {
+    result: Option<Vec<ChartResult>>,
+    error: Option<ChartError>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ChartError {
+    code: String,
+    description: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ChartResult {
+    meta: Meta,
+    #[serde(default)]
+    timestamp: Vec<i64>,
+    indicators: Indicators,
+}
+
+impl ChartResult {
+    // Yahoo reports bars with no trades as nulls, so those are skipped.
+    fn bars(&self) -> Vec<Bar> {
+        let quote = match self.indicators.quote.first() {
+            Some(quote) => quote,
+            None => return Vec::new(),
+        };
+
+        self.timestamp
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &timestamp)| {
+                Some(Bar {
+                    timestamp,
+                    open: (*quote.open.get(i)?)?,
+                    high: (*quote.high.get(i)?)?,
+                    low: (*quote.low.get(i)?)?,
+                    close: (*quote.close.get(i)?)?,
+                    volume: (*quote.volume.get(i)?)?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Meta {
+    symbol: String,
+    regular_market_price: Option<f64>,
+    chart_previous_close: Option<f64>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Indicators {
+    quote: Vec<IndicatorQuote>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct IndicatorQuote {
+    open: Vec<Option<f64>>,
+    high: Vec<Option<f64>>,
+    low: Vec<Option<f64>>,
+    close: Vec<Option<f64>>,
+    volume: Vec<Option<u64>>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Bar {
+    timestamp: i64,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHART: &str = include_str!("fixtures/chart.json");
+
+    #[test]
+    fn test_parse_ohlc() {
+        let ohlc = parse_ohlc(CHART, OhlcInterval::FiveMinute).unwrap();
+
+        // The null bar at 09:40 is skipped.
+        assert_eq!(3, ohlc.len());
+        assert_eq!("2021-06-01T09:30:00", ohlc[0].time);
+        assert_eq!("2021-06-01T09:45:00", ohlc[2].time);
+        assert_eq!(422.57, ohlc[0].open);
+        assert_eq!(422.72, ohlc[0].high);
+        assert_eq!(421.61, ohlc[0].low);
+        assert_eq!(421.9, ohlc[0].close);
+        assert_eq!(ohlc[0].close, ohlc[0].price);
+        assert_eq!(1203045, ohlc[0].volume);
+        assert_eq!(None, ohlc[0].vwap);
+    }
+
+    #[test]
+    fn test_parse_quote() {
+        let quote = parse_quote(CHART).unwrap();
+
+        assert_eq!("SPY", quote.symbol);
+        assert_eq!(Some(421.77), quote.last);
+        assert!((quote.change.unwrap() - 2.1).abs() < 1e-9);
+        assert_eq!(Some(421.77), quote.close);
+        assert_eq!(801256, quote.volume);
+    }
+
+    #[test]
+    fn test_chart_url_with_dates() {
+        let start = NaiveDate::from_ymd_opt(2021, 6, 1);
+        let end = NaiveDate::from_ymd_opt(2021, 6, 4);
+        let now = 1700000000;
+
+        let window = chart_window("5d", 5, start, end, now).unwrap();
+        assert_eq!(
+            "https://query1.finance.yahoo.com/v8/finance/chart/SPY?interval=5m&period1=1622520000&period2=1622865600",
+            chart_url("spy", "5m", &window)
+        );
+
+        assert_eq!("range=5d", chart_window("5d", 5, None, None, now).unwrap());
+        assert_eq!(
+            format!("period1=1622520000&period2={}", now),
+            chart_window("5d", 5, start, None, now).unwrap()
+        );
+        assert_eq!(
+            "period1=1622520000&period2=1622865600",
+            chart_window("1d", 4, None, end, now).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_chart_error() {
+        let body = r#"{"chart":{"result":null,"error":{"code":"Not Found","description":"No data found, symbol may be delisted"}}}"#;
+        let error = parse_quote(body).unwrap_err().to_string();
+        assert!(error.contains("No data found"), "{}", error);
+    }
+}
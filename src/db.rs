@@ -1,19 +1,203 @@
 pub mod file;
+pub mod sqlite;
 
-use crate::{data_apis::tradier, types::OptionInfo};
-use std::{sync::Arc, time::Duration};
+use crate::{
+    data_apis::{tradier, MarketDataProvider},
+    types::OptionInfo,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::America::New_York;
+use futures::{stream, StreamExt};
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 use tokio::sync::Mutex;
 
 pub use file::FileDb;
+pub use sqlite::SqliteDb;
 
-pub async fn option_chain(symbol: &str, db: Arc<Mutex<FileDb>>) -> anyhow::Result<Vec<OptionInfo>> {
-    let has_symbol = {
+pub const DEFAULT_TTL_SECS: i64 = 15 * 60;
+pub const DEFAULT_DIR: &str = "data";
+const BACKEND_ENV: &str = "DB_BACKEND";
+const PATH_ENV: &str = "DB_PATH";
+const RETENTION_ENV: &str = "DB_RETENTION_DAYS";
+const DEFAULT_RETENTION_DAYS: i64 = 7;
+const TTL_ENV: &str = "DB_TTL_SECS";
+
+pub type Symbol = String;
+pub type OptionSnapshot = Vec<OptionInfo>;
+pub type SharedDb = Arc<Mutex<dyn Database>>;
+
+/// Storage for option chain snapshots.
+pub trait Database: Send {
+    fn add_option_info_at(
+        &mut self,
+        symbol: &str,
+        data: OptionSnapshot,
+        fetched_at: DateTime<Utc>,
+    ) -> anyhow::Result<()>;
+
+    fn add_option_info(&mut self, symbol: &str, data: OptionSnapshot) -> anyhow::Result<()> {
+        self.add_option_info_at(symbol, data, Utc::now())
+    }
+
+    fn has_symbol(&self, symbol: &str) -> bool;
+
+    /// The latest option chain for the symbol.
+    fn option_chain(&self, symbol: &str) -> Option<OptionSnapshot>;
+
+    /// The latest option chain, or `None` if it was fetched more than the TTL
+    /// before `now`. Snapshots saved without a fetch time count as stale.
+    fn fresh_option_chain(&self, symbol: &str, now: DateTime<Utc>) -> Option<OptionSnapshot>;
+
+    /// The last option chain fetched on `date`, in New York time.
+    fn option_chain_at(&self, symbol: &str, date: NaiveDate) -> Option<OptionSnapshot>;
+
+    fn symbols(&self) -> Vec<String>;
+
+    /// When the latest option chain for the symbol was fetched. `None` for
+    /// unknown symbols and snapshots saved without a fetch time.
+    fn last_updated(&self, symbol: &str) -> Option<DateTime<Utc>>;
+
+    /// Drops every snapshot for the symbol. Returns whether there were any.
+    fn delete_symbol(&mut self, symbol: &str) -> anyhow::Result<bool>;
+}
+
+/// Opens the backend named by `DB_BACKEND`, either `file` (the default) or
+/// `sqlite`, in the directory from `DB_PATH`.
+pub fn load() -> anyhow::Result<SharedDb> {
+    let backend = std::env::var(BACKEND_ENV).unwrap_or_else(|_| "file".to_string());
+    load_from(&backend, &data_dir())
+}
+
+/// Where the db files live, `DB_PATH` or `data` by default.
+pub fn data_dir() -> PathBuf {
+    std::env::var_os(PATH_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_DIR))
+}
+
+fn load_from(backend: &str, dir: &Path) -> anyhow::Result<SharedDb> {
+    check_writable(dir)?;
+
+    let db: SharedDb = match backend.to_lowercase().as_str() {
+        "file" => Arc::new(Mutex::new(
+            FileDb::load(dir).unwrap_or_else(|_| FileDb::new(dir.join(file::FILE_NAME))),
+        )),
+        "sqlite" => Arc::new(Mutex::new(SqliteDb::load(dir)?)),
+        _ => anyhow::bail!("Invalid db backend: {}", backend),
+    };
+
+    Ok(db)
+}
+
+fn ttl() -> chrono::Duration {
+    let secs = std::env::var(TTL_ENV)
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(DEFAULT_TTL_SECS);
+    chrono::Duration::seconds(secs)
+}
+
+// Creates the directory if needed and writes a probe file, so a bad path
+// fails at startup instead of on the first update.
+fn check_writable(dir: &Path) -> anyhow::Result<()> {
+    let error = |e: std::io::Error| {
+        anyhow::anyhow!("Db directory {} is not writable: {}", dir.display(), e)
+    };
+
+    std::fs::create_dir_all(dir).map_err(error)?;
+    let probe = dir.join(format!(".write_check.{}", std::process::id()));
+    std::fs::write(&probe, b"").map_err(error)?;
+    std::fs::remove_file(&probe).map_err(error)?;
+    Ok(())
+}
+
+// Snapshots older than this are pruned on write. Unset keeps a week; 0
+// keeps everything.
+fn retention() -> Option<chrono::Duration> {
+    retention_from(std::env::var(RETENTION_ENV).ok().as_deref())
+}
+
+fn retention_from(days: Option<&str>) -> Option<chrono::Duration> {
+    let days = days
+        .and_then(|days| days.parse().ok())
+        .unwrap_or(DEFAULT_RETENTION_DAYS);
+    if days == 0 {
+        None
+    } else {
+        Some(chrono::Duration::days(days))
+    }
+}
+
+fn market_date(time: DateTime<Utc>) -> NaiveDate {
+    time.with_timezone(&New_York).date_naive()
+}
+
+/// The stored chain for `symbol`, downloaded from `provider` when missing or
+/// stale.
+pub async fn option_chain(
+    symbol: &str,
+    db: SharedDb,
+    provider: &dyn MarketDataProvider,
+) -> anyhow::Result<Vec<OptionInfo>> {
+    let upper_symbol = symbol.to_uppercase();
+    cached_option_chain(symbol, db, Utc::now(), || async move {
+        provider.get_option_chain(&upper_symbol).await
+    })
+    .await
+}
+
+/// The stored chain fetched on `date`. Unlike `option_chain`, this never
+/// downloads since past chains can't be fetched again.
+pub async fn option_chain_at(
+    symbol: &str,
+    date: NaiveDate,
+    db: SharedDb,
+) -> anyhow::Result<Vec<OptionInfo>> {
+    let db = db.lock().await;
+    db.option_chain_at(symbol, date)
+        .ok_or_else(|| anyhow::anyhow!("No data stored for {} on {}", symbol, date))
+}
+
+// Downloads a new chain when the cached one is missing or older than the db's
+// TTL. If the download fails, stale data is better than nothing.
+async fn cached_option_chain<F, Fut>(
+    symbol: &str,
+    db: SharedDb,
+    now: DateTime<Utc>,
+    fetch: F,
+) -> anyhow::Result<Vec<OptionInfo>>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = anyhow::Result<Vec<OptionInfo>>>,
+{
+    let is_fresh = {
         let db = db.lock().await;
-        db.has_symbol(symbol)
+        db.fresh_option_chain(symbol, now).is_some()
     };
 
-    if !has_symbol {
-        update_symbol(symbol, db.clone()).await?;
+    if !is_fresh {
+        log::info!("Updating data for {}", symbol);
+        match fetch().await {
+            Ok(option_chain) => {
+                let mut db = db.lock().await;
+                match db.add_option_info_at(symbol, option_chain, now) {
+                    Ok(()) => log::info!("Successfully updated data for {}", symbol),
+                    Err(e) => log::error!("Failed to store data for {}: {}", symbol, e),
+                }
+            }
+            Err(e) => {
+                let db = db.lock().await;
+                if !db.has_symbol(symbol) {
+                    return Err(e);
+                }
+                log::error!("Serving stale data for {}: {}", symbol, e);
+            }
+        }
     }
 
     let db = db.lock().await;
@@ -21,10 +205,87 @@ pub async fn option_chain(symbol: &str, db: Arc<Mutex<FileDb>>) -> anyhow::Resul
         .option_chain(symbol)
         .ok_or_else(|| anyhow::anyhow!("Error loading data for {}", symbol))?;
 
-    Ok(option_chain.clone())
+    Ok(option_chain)
+}
+
+const PREFETCH_CONCURRENCY: usize = 8;
+
+/// How prefetching one symbol went.
+#[derive(Debug, PartialEq)]
+pub enum PrefetchStatus {
+    Fetched,
+    /// Already fresh in the db, so nothing was downloaded.
+    Cached,
+    Failed(String),
+}
+
+/// Downloads option chains for `symbols`, a few at a time to stay under the
+/// broker's rate limit. Fresh chains are skipped unless `force` is set.
+/// Results are sorted by symbol.
+pub async fn prefetch(
+    symbols: &[String],
+    force: bool,
+    db: SharedDb,
+    provider: &dyn MarketDataProvider,
+) -> Vec<(Symbol, PrefetchStatus)> {
+    prefetch_with(symbols, force, db, Utc::now(), |symbol| async move {
+        provider.get_option_chain(&symbol.to_uppercase()).await
+    })
+    .await
+}
+
+async fn prefetch_with<F, Fut>(
+    symbols: &[String],
+    force: bool,
+    db: SharedDb,
+    now: DateTime<Utc>,
+    fetch: F,
+) -> Vec<(Symbol, PrefetchStatus)>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = anyhow::Result<Vec<OptionInfo>>>,
+{
+    let fetch = &fetch;
+    let mut results: Vec<_> = stream::iter(symbols.iter().cloned())
+        .map(|symbol| {
+            let db = db.clone();
+            async move {
+                if !force && db.lock().await.fresh_option_chain(&symbol, now).is_some() {
+                    return (symbol, PrefetchStatus::Cached);
+                }
+
+                let result = match fetch(symbol.clone()).await {
+                    Ok(option_chain) => {
+                        db.lock()
+                            .await
+                            .add_option_info_at(&symbol, option_chain, now)
+                    }
+                    Err(e) => Err(e),
+                };
+                let status = match result {
+                    Ok(()) => PrefetchStatus::Fetched,
+                    Err(e) => {
+                        log::error!("Failed to prefetch {}: {}", symbol, e);
+                        PrefetchStatus::Failed(e.to_string())
+                    }
+                };
+                (symbol, status)
+            }
+        })
+        .buffer_unordered(PREFETCH_CONCURRENCY)
+        .collect()
+        .await;
+
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+    results
 }
 
-pub fn start_db_update_loop(db: Arc<Mutex<FileDb>>) -> anyhow::Result<()> {
+// Chains come from `provider`. The market clock that paces the loop is
+// always Tradier's, since the other providers don't have one.
+pub fn start_db_update_loop(
+    db: SharedDb,
+    provider: Box<dyn MarketDataProvider>,
+) -> anyhow::Result<()> {
     tokio::task::spawn(async move {
         let mut symbol_delay = tokio::time::interval(Duration::from_secs(60));
         loop {
@@ -37,7 +298,7 @@ pub fn start_db_update_loop(db: Arc<Mutex<FileDb>>) -> anyhow::Result<()> {
 
             for symbol in symbols {
                 symbol_delay.tick().await;
-                if let Err(e) = update_symbol(&symbol, db.clone()).await {
+                if let Err(e) = update_symbol(&symbol, db.clone(), provider.as_ref()).await {
                     log::error!("{}", e);
                 }
             }
@@ -74,12 +335,166 @@ pub async fn duration_until_next_check() -> Duration {
     }
 }
 
-pub async fn update_symbol(symbol: &str, db: Arc<Mutex<FileDb>>) -> anyhow::Result<()> {
+pub async fn update_symbol(
+    symbol: &str,
+    db: SharedDb,
+    provider: &dyn MarketDataProvider,
+) -> anyhow::Result<()> {
     log::info!("Updating data for {}", symbol);
-    let option_chain = tradier::get_option_chain(&symbol.to_uppercase()).await?;
+    let option_chain = provider.get_option_chain(&symbol.to_uppercase()).await?;
     let mut db = db.lock().await;
-    db.add_option_info(&symbol, option_chain);
+    db.add_option_info(symbol, option_chain)?;
     log::info!("Successfully updated data for {}", symbol);
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_load_from_configured_dir() {
+        let dir = std::env::temp_dir().join(format!("market_analyzer_db_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        for backend in &["file", "sqlite"] {
+            let db = load_from(backend, &dir).unwrap();
+            db.lock()
+                .await
+                .add_option_info("TST", vec![OptionInfo::test()])
+                .unwrap();
+        }
+
+        assert!(dir.join(file::FILE_NAME).is_file());
+        assert!(dir.join(sqlite::FILE_NAME).is_file());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_from_unwritable_dir_fails() {
+        // A directory can't be created under a regular file.
+        let file =
+            std::env::temp_dir().join(format!("market_analyzer_file_{}", std::process::id()));
+        std::fs::write(&file, b"").unwrap();
+
+        let error = load_from("file", &file.join("db")).err().unwrap();
+        assert!(error.to_string().contains("not writable"), "{}", error);
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_retention_defaults_to_a_week() {
+        assert_eq!(Some(chrono::Duration::days(7)), retention_from(None));
+        assert_eq!(Some(chrono::Duration::days(7)), retention_from(Some("x")));
+        assert_eq!(Some(chrono::Duration::days(30)), retention_from(Some("30")));
+        assert_eq!(None, retention_from(Some("0")));
+    }
+
+    #[tokio::test]
+    async fn test_prefetch() {
+        let db: SharedDb = Arc::new(Mutex::new(FileDb::new("data/test_prefetch_db.gz")));
+        let now = Utc::now();
+        db.lock()
+            .await
+            .add_option_info_at("CACHED", vec![OptionInfo::test()], now)
+            .unwrap();
+        let mut symbols: Vec<String> = (0..20).map(|i| format!("SYM{:02}", i)).collect();
+        symbols.push("BAD".to_string());
+        symbols.push("CACHED".to_string());
+
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+        let fetch = |symbol: String| {
+            let (in_flight, max_in_flight) = (&in_flight, &max_in_flight);
+            async move {
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(current, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                if symbol == "BAD" {
+                    anyhow::bail!("Unknown symbol");
+                }
+                Ok(vec![OptionInfo::test()])
+            }
+        };
+
+        let results = prefetch_with(&symbols, false, db.clone(), now, fetch).await;
+
+        assert_eq!(22, results.len());
+        assert_eq!(
+            (
+                "BAD".to_string(),
+                PrefetchStatus::Failed("Unknown symbol".to_string())
+            ),
+            results[0]
+        );
+        assert_eq!(("CACHED".to_string(), PrefetchStatus::Cached), results[1]);
+        assert!(results[2..]
+            .iter()
+            .all(|(_, status)| *status == PrefetchStatus::Fetched));
+        assert!(db.lock().await.has_symbol("SYM19"));
+        assert!(max_in_flight.load(Ordering::SeqCst) <= PREFETCH_CONCURRENCY);
+
+        let forced = prefetch_with(&symbols[21..], true, db, now, fetch).await;
+        assert_eq!(("CACHED".to_string(), PrefetchStatus::Fetched), forced[0]);
+    }
+
+    #[tokio::test]
+    async fn test_option_chain_refetches_after_ttl() {
+        let db = FileDb::new("data/test_ttl_db.gz").with_ttl(chrono::Duration::minutes(15));
+        let db: SharedDb = Arc::new(Mutex::new(db));
+        let fetches = AtomicUsize::new(0);
+        let fetch = || async {
+            fetches.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![OptionInfo::test()])
+        };
+        let start = Utc::now();
+
+        cached_option_chain("TST", db.clone(), start, fetch)
+            .await
+            .unwrap();
+        assert_eq!(1, fetches.load(Ordering::SeqCst));
+
+        let later = start + chrono::Duration::minutes(10);
+        cached_option_chain("TST", db.clone(), later, fetch)
+            .await
+            .unwrap();
+        assert_eq!(1, fetches.load(Ordering::SeqCst));
+
+        let expired = start + chrono::Duration::minutes(16);
+        let chain = cached_option_chain("TST", db.clone(), expired, fetch)
+            .await
+            .unwrap();
+        assert_eq!(2, fetches.load(Ordering::SeqCst));
+        assert_eq!("TST", chain[0].symbol);
+    }
+
+    #[tokio::test]
+    async fn test_option_chain_serves_stale_data_when_refetch_fails() {
+        let db = FileDb::new("data/test_stale_db.gz").with_ttl(chrono::Duration::minutes(15));
+        let db: SharedDb = Arc::new(Mutex::new(db));
+        let start = Utc::now();
+
+        cached_option_chain("TST", db.clone(), start, || async {
+            Ok(vec![OptionInfo::test()])
+        })
+        .await
+        .unwrap();
+
+        let expired = start + chrono::Duration::minutes(16);
+        let chain = cached_option_chain("TST", db.clone(), expired, || async {
+            anyhow::bail!("Download failed")
+        })
+        .await
+        .unwrap();
+        assert_eq!("TST", chain[0].symbol);
+
+        let missing = cached_option_chain("NEW", db, expired, || async {
+            anyhow::bail!("Download failed")
+        })
+        .await;
+        assert!(missing.is_err());
+    }
+}
@@ -1,23 +1,80 @@
 use std::{
     collections::HashMap,
+    ffi::OsString,
     io::{Read, Write},
     path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
 };
 
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 use serde::{Deserialize, Serialize};
 
-use crate::types::OptionInfo;
+use super::{market_date, retention, ttl, Database, OptionSnapshot, Symbol};
 
-pub const DEFAULT_FILE_PATH: &str = "data/db.gz";
+pub const FILE_NAME: &str = "db.gz";
 
-pub type Symbol = String;
-pub type OptionSnapshot = Vec<OptionInfo>;
+static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FileDb {
+    // Always where the db was loaded from, never what an older file recorded.
+    #[serde(skip)]
     file_path: PathBuf,
-    options: HashMap<Symbol, Vec<OptionSnapshot>>,
+    options: HashMap<Symbol, Vec<Snapshot>>,
+    #[serde(skip, default = "ttl")]
+    ttl: Duration,
+    #[serde(skip, default = "retention")]
+    retention: Option<Duration>,
+    #[serde(skip, default = "compress")]
+    compress: bool,
+}
+
+const COMPRESS_ENV: &str = "DB_COMPRESS";
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+// Gzip unless DB_COMPRESS is false. Reads handle either format.
+fn compress() -> bool {
+    std::env::var(COMPRESS_ENV)
+        .ok()
+        .and_then(|compress| compress.parse().ok())
+        .unwrap_or(true)
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "StoredSnapshot")]
+struct Snapshot {
+    fetched_at: Option<DateTime<Utc>>,
+    options: OptionSnapshot,
+}
+
+// Older db files stored bare option chains without a fetch time.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredSnapshot {
+    Timestamped {
+        fetched_at: Option<DateTime<Utc>>,
+        options: OptionSnapshot,
+    },
+    Legacy(OptionSnapshot),
+}
+
+impl From<StoredSnapshot> for Snapshot {
+    fn from(stored: StoredSnapshot) -> Self {
+        match stored {
+            StoredSnapshot::Timestamped {
+                fetched_at,
+                options,
+            } => Self {
+                fetched_at,
+                options,
+            },
+            StoredSnapshot::Legacy(options) => Self {
+                fetched_at: None,
+                options,
+            },
+        }
+    }
 }
 
 impl FileDb {
@@ -29,81 +86,199 @@ impl FileDb {
         path: impl AsRef<Path>,
         options: HashMap<Symbol, Vec<OptionSnapshot>>,
     ) -> Self {
+        let options = options
+            .into_iter()
+            .map(|(symbol, snapshots)| {
+                let snapshots = snapshots
+                    .into_iter()
+                    .map(|options| Snapshot {
+                        fetched_at: None,
+                        options,
+                    })
+                    .collect();
+                (symbol, snapshots)
+            })
+            .collect();
+
         Self {
             file_path: path.as_ref().into(),
             options,
+            ttl: ttl(),
+            retention: retention(),
+            compress: compress(),
         }
     }
 
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn with_retention(mut self, retention: Option<Duration>) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    pub fn with_compression(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
     pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
-        let bytes = std::fs::read(path)?;
+        let bytes = std::fs::read(&path)?;
+
+        let mut db: Self = if bytes.starts_with(&GZIP_MAGIC) {
+            let mut decoder = GzDecoder::new(&*bytes);
+            let mut decoded_bytes = String::new();
+            decoder.read_to_string(&mut decoded_bytes)?;
+            serde_json::from_str(&decoded_bytes)?
+        } else {
+            serde_json::from_slice(&bytes)?
+        };
+        db.file_path = path.as_ref().to_path_buf();
+
+        Ok(db)
+    }
+
+    pub fn load(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Self::from_file(dir.as_ref().join(FILE_NAME))
+    }
 
-        let mut decoder = GzDecoder::new(&*bytes);
-        let mut decoded_bytes = String::new();
-        decoder.read_to_string(&mut decoded_bytes)?;
+    fn write(&self) -> anyhow::Result<()> {
+        let json = serde_json::to_vec_pretty(&self)?;
 
-        Ok(serde_json::from_str(&decoded_bytes)?)
+        // Debug
+        // std::fs::write("data/db.json", &json)?;
+
+        let bytes = if self.compress {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+            encoder.write_all(&json)?;
+            encoder.finish()?
+        } else {
+            json
+        };
+
+        if let Some(parent) = self.file_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Readers (including the /db download) must never see a half-written
+        // file, so write beside it and rename over it, which is atomic.
+        let temp_path = self.temp_path();
+        if let Err(e) = std::fs::write(&temp_path, &bytes)
+            .and_then(|_| std::fs::rename(&temp_path, &self.file_path))
+        {
+            let _ = std::fs::remove_file(&temp_path);
+            return Err(e.into());
+        }
+
+        Ok(())
     }
 
-    pub fn load() -> anyhow::Result<Self> {
-        Self::from_file(DEFAULT_FILE_PATH)
+    fn temp_path(&self) -> PathBuf {
+        let mut file_name = OsString::from(".");
+        file_name.push(self.file_path.file_name().unwrap_or_default());
+        file_name.push(format!(
+            ".{}.{}.tmp",
+            std::process::id(),
+            TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        self.file_path.with_file_name(file_name)
     }
+}
 
-    pub fn add_option_info(&mut self, symbol: &str, data: Vec<OptionInfo>) {
+impl Database for FileDb {
+    fn add_option_info_at(
+        &mut self,
+        symbol: &str,
+        data: OptionSnapshot,
+        fetched_at: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
         let symbol = symbol.to_uppercase();
 
-        let entry = self.options.entry(symbol).or_insert_with(Vec::new);
+        let entry = self.options.entry(symbol).or_default();
 
-        entry.push(data);
-        if let Err(e) = self.write() {
-            log::error!("{}", e);
+        entry.push(Snapshot {
+            fetched_at: Some(fetched_at),
+            options: data,
+        });
+        if let Some(retention) = self.retention {
+            let cutoff = fetched_at - retention;
+            entry.retain(|s| s.fetched_at.is_some_and(|t| t >= cutoff));
         }
+        self.write()
     }
 
-    pub fn has_symbol(&self, symbol: &str) -> bool {
+    fn has_symbol(&self, symbol: &str) -> bool {
         let symbol = symbol.to_uppercase();
 
         self.options.contains_key(&symbol)
     }
 
-    pub fn option_chain(&self, symbol: &str) -> Option<&Vec<OptionInfo>> {
+    fn option_chain(&self, symbol: &str) -> Option<OptionSnapshot> {
+        let symbol = symbol.to_uppercase();
+
+        self.options
+            .get(&symbol)
+            .and_then(|v| v.last())
+            .map(|s| s.options.clone())
+    }
+
+    fn fresh_option_chain(&self, symbol: &str, now: DateTime<Utc>) -> Option<OptionSnapshot> {
+        let symbol = symbol.to_uppercase();
+
+        let snapshot = self.options.get(&symbol).and_then(|v| v.last())?;
+
+        if now - snapshot.fetched_at? > self.ttl {
+            return None;
+        }
+
+        Some(snapshot.options.clone())
+    }
+
+    fn option_chain_at(&self, symbol: &str, date: NaiveDate) -> Option<OptionSnapshot> {
         let symbol = symbol.to_uppercase();
 
-        self.options.get(&symbol).map(|v| v.last()).flatten()
+        self.options
+            .get(&symbol)?
+            .iter()
+            .rev()
+            .find(|s| s.fetched_at.is_some_and(|t| market_date(t) == date))
+            .map(|s| s.options.clone())
     }
 
-    pub fn symbols(&self) -> Vec<String> {
+    fn symbols(&self) -> Vec<String> {
         self.options.keys().cloned().collect()
     }
 
-    fn write(&self) -> anyhow::Result<()> {
-        let json = serde_json::to_vec_pretty(&self)?;
+    fn last_updated(&self, symbol: &str) -> Option<DateTime<Utc>> {
+        let symbol = symbol.to_uppercase();
 
-        // Debug
-        // std::fs::write("data/db.json", &json)?;
+        self.options.get(&symbol)?.last()?.fetched_at
+    }
 
-        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
-        encoder.write_all(&json)?;
-        let compressed_bytes = encoder.finish()?;
+    fn delete_symbol(&mut self, symbol: &str) -> anyhow::Result<bool> {
+        let symbol = symbol.to_uppercase();
 
-        if let Some(parent) = self.file_path.parent() {
-            std::fs::create_dir_all(parent)?;
+        if self.options.remove(&symbol).is_none() {
+            return Ok(false);
         }
-        std::fs::write(&self.file_path, &compressed_bytes)?;
-
-        Ok(())
+        self.write()?;
+        Ok(true)
     }
 }
 
 impl Default for FileDb {
     fn default() -> Self {
-        Self::new(DEFAULT_FILE_PATH)
+        Self::new(Path::new(super::DEFAULT_DIR).join(FILE_NAME))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::OptionInfo;
+    use chrono::TimeZone;
 
     pub const TEST_FILE_PATH: &str = "data/test_db.gz";
 
@@ -111,9 +286,9 @@ mod tests {
     fn db() {
         let mut db = FileDb::new(TEST_FILE_PATH);
 
-        db.add_option_info("TST", vec![OptionInfo::test()]);
-        db.add_option_info("TST", vec![OptionInfo::test()]);
-        db.add_option_info("TST", vec![OptionInfo::test()]);
+        db.add_option_info("TST", vec![OptionInfo::test()]).unwrap();
+        db.add_option_info("TST", vec![OptionInfo::test()]).unwrap();
+        db.add_option_info("TST", vec![OptionInfo::test()]).unwrap();
 
         let oi = db.option_chain("TST").unwrap();
         assert_eq!(oi[0].symbol, "TST");
@@ -124,4 +299,208 @@ mod tests {
 
         assert_eq!(db2.option_chain("TST").unwrap()[0].symbol, "TST");
     }
+
+    #[test]
+    fn test_fresh_option_chain_expires_after_ttl() {
+        let mut db = FileDb::new("data/test_ttl_file_db.gz").with_ttl(Duration::minutes(5));
+        let fetched_at = Utc::now();
+
+        db.add_option_info_at("TST", vec![OptionInfo::test()], fetched_at)
+            .unwrap();
+
+        assert!(db.fresh_option_chain("tst", fetched_at).is_some());
+        assert!(db
+            .fresh_option_chain("TST", fetched_at + Duration::minutes(5))
+            .is_some());
+        assert!(db
+            .fresh_option_chain("TST", fetched_at + Duration::minutes(6))
+            .is_none());
+        assert!(db.option_chain("TST").is_some());
+    }
+
+    #[test]
+    fn test_last_updated() {
+        let mut db = FileDb::new("data/test_last_updated_file_db.gz");
+        let fetched_at = Utc.with_ymd_and_hms(2021, 6, 1, 14, 0, 0).unwrap();
+        assert_eq!(None, db.last_updated("TST"));
+
+        db.add_option_info_at("TST", vec![OptionInfo::test()], fetched_at)
+            .unwrap();
+
+        assert_eq!(Some(fetched_at), db.last_updated("tst"));
+    }
+
+    #[test]
+    fn test_load_writes_back_to_its_own_dir() {
+        let dir = Path::new("data/test_moved_db");
+        let stale_path = "data/test_moved_db_stale.gz";
+        std::fs::create_dir_all(dir).unwrap();
+
+        // Files written before file_path was skipped still carry a path.
+        let mut json = serde_json::to_value(FileDb::new(stale_path)).unwrap();
+        json["file_path"] = stale_path.into();
+        std::fs::write(dir.join(FILE_NAME), json.to_string()).unwrap();
+        let _ = std::fs::remove_file(stale_path);
+
+        let mut db = FileDb::load(dir).unwrap();
+        db.add_option_info("TST", vec![OptionInfo::test()]).unwrap();
+
+        assert!(FileDb::load(dir).unwrap().has_symbol("TST"));
+        assert!(!Path::new(stale_path).exists());
+    }
+
+    #[test]
+    fn test_compressed_and_plain_files_round_trip() {
+        for &(path, compress) in &[
+            ("data/test_compressed_file_db.gz", true),
+            ("data/test_plain_file_db.json", false),
+        ] {
+            let mut db = FileDb::new(path).with_compression(compress);
+            let mut option = OptionInfo::test();
+            option.strike = 105.0;
+            db.add_option_info("TST", vec![option.clone()]).unwrap();
+
+            let bytes = std::fs::read(path).unwrap();
+            assert_eq!(compress, bytes.starts_with(&GZIP_MAGIC));
+
+            let read = FileDb::from_file(path)
+                .unwrap()
+                .option_chain("TST")
+                .unwrap();
+            assert_eq!(
+                serde_json::to_value(vec![option]).unwrap(),
+                serde_json::to_value(read).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_delete_symbol() {
+        let path = "data/test_delete_symbol_file_db.gz";
+        let mut db = FileDb::new(path);
+        db.add_option_info("TST", vec![OptionInfo::test()]).unwrap();
+        db.add_option_info("OTHER", vec![OptionInfo::test()])
+            .unwrap();
+
+        assert!(db.delete_symbol("tst").unwrap());
+        assert!(!db.delete_symbol("TST").unwrap());
+
+        let db = FileDb::from_file(path).unwrap();
+        assert!(!db.has_symbol("TST"));
+        assert!(db.has_symbol("OTHER"));
+    }
+
+    #[test]
+    fn test_snapshots_without_fetch_time_are_stale() {
+        let mut options = HashMap::new();
+        options.insert("TST".to_string(), vec![vec![OptionInfo::test()]]);
+        let db = FileDb::from_data("data/test_ttl_file_db.gz", options);
+
+        assert!(db.fresh_option_chain("TST", Utc::now()).is_none());
+        assert!(db.option_chain("TST").is_some());
+    }
+
+    #[test]
+    fn test_concurrent_reads_never_see_partial_writes() {
+        let path = "data/test_concurrent_db.gz";
+        let mut db = FileDb::new(path);
+        db.add_option_info("TST", vec![OptionInfo::test(); 100])
+            .unwrap();
+
+        let writer = std::thread::spawn(move || {
+            for _ in 0..10 {
+                db.add_option_info("TST", vec![OptionInfo::test(); 100])
+                    .unwrap();
+            }
+        });
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                std::thread::spawn(move || {
+                    for _ in 0..10 {
+                        let db = FileDb::from_file(path).unwrap();
+                        assert_eq!(100, db.option_chain("TST").unwrap().len());
+                    }
+                })
+            })
+            .collect();
+
+        writer.join().unwrap();
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        let leftovers = std::fs::read_dir("data")
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                name.starts_with(".test_concurrent_db.gz") && name.ends_with(".tmp")
+            })
+            .count();
+        assert_eq!(0, leftovers);
+    }
+
+    #[test]
+    fn test_option_chain_at() {
+        let mut db = FileDb::new("data/test_history_db.gz").with_retention(None);
+        let monday = Utc.with_ymd_and_hms(2021, 6, 7, 15, 0, 0).unwrap();
+        let tuesday = Utc.with_ymd_and_hms(2021, 6, 8, 15, 0, 0).unwrap();
+
+        let mut earlier = OptionInfo::test();
+        earlier.open_interest = 10;
+        let mut later = OptionInfo::test();
+        later.open_interest = 20;
+
+        db.add_option_info_at("TST", vec![earlier], monday).unwrap();
+        db.add_option_info_at("TST", vec![later], tuesday).unwrap();
+
+        let date = NaiveDate::from_ymd_opt(2021, 6, 7).unwrap();
+        assert_eq!(
+            10,
+            db.option_chain_at("TST", date).unwrap()[0].open_interest
+        );
+        let date = NaiveDate::from_ymd_opt(2021, 6, 8).unwrap();
+        assert_eq!(
+            20,
+            db.option_chain_at("TST", date).unwrap()[0].open_interest
+        );
+        let date = NaiveDate::from_ymd_opt(2021, 6, 9).unwrap();
+        assert!(db.option_chain_at("TST", date).is_none());
+        assert_eq!(20, db.option_chain("TST").unwrap()[0].open_interest);
+
+        let db = FileDb::from_file("data/test_history_db.gz").unwrap();
+        let date = NaiveDate::from_ymd_opt(2021, 6, 7).unwrap();
+        assert_eq!(
+            10,
+            db.option_chain_at("TST", date).unwrap()[0].open_interest
+        );
+    }
+
+    #[test]
+    fn test_retention_prunes_old_snapshots() {
+        let mut db =
+            FileDb::new("data/test_retention_db.gz").with_retention(Some(Duration::days(7)));
+        let start = Utc.with_ymd_and_hms(2021, 6, 1, 15, 0, 0).unwrap();
+
+        db.add_option_info_at("TST", vec![OptionInfo::test()], start)
+            .unwrap();
+        db.add_option_info_at("TST", vec![OptionInfo::test()], start + Duration::days(8))
+            .unwrap();
+
+        assert!(db.option_chain_at("TST", start.date_naive()).is_none());
+        assert_eq!(1, db.options["TST"].len());
+    }
+
+    #[test]
+    fn test_reads_snapshots_without_fetch_time() {
+        let json = serde_json::json!({
+            "file_path": "data/test_legacy_db.gz",
+            "options": { "TST": [[OptionInfo::test()]] },
+        });
+        let db: FileDb = serde_json::from_value(json).unwrap();
+
+        assert_eq!("TST", db.option_chain("TST").unwrap()[0].symbol);
+        assert!(db.fresh_option_chain("TST", Utc::now()).is_none());
+    }
 }
@@ -0,0 +1,362 @@
+use std::path::Path;
+
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::America::New_York;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::{retention, ttl, Database, OptionSnapshot};
+
+pub const FILE_NAME: &str = "db.sqlite";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS option_snapshots (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        symbol TEXT NOT NULL,
+        fetched_at TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS option_snapshots_symbol ON option_snapshots (symbol, id);
+";
+
+/// Option chain snapshots in SQLite, one row per snapshot with the chain
+/// stored as JSON.
+pub struct SqliteDb {
+    connection: Connection,
+    ttl: Duration,
+    retention: Option<Duration>,
+}
+
+impl SqliteDb {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> anyhow::Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    pub fn load(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Self::open(dir.as_ref().join(FILE_NAME))
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn with_retention(mut self, retention: Option<Duration>) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    fn from_connection(connection: Connection) -> anyhow::Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            connection,
+            ttl: ttl(),
+            retention: retention(),
+        })
+    }
+
+    fn latest_snapshot(&self, symbol: &str) -> anyhow::Result<Option<(String, String)>> {
+        let snapshot = self
+            .connection
+            .query_row(
+                "SELECT fetched_at, data FROM option_snapshots
+                 WHERE symbol = ?1 ORDER BY id DESC LIMIT 1",
+                params![symbol.to_uppercase()],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        Ok(snapshot)
+    }
+
+    fn parse_snapshot(data: &str) -> Option<OptionSnapshot> {
+        serde_json::from_str(data)
+            .map_err(|e| log::error!("{}", e))
+            .ok()
+    }
+}
+
+// Fetch times are stored as UTC RFC 3339 strings, so they sort as text.
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339()
+}
+
+impl Database for SqliteDb {
+    fn add_option_info_at(
+        &mut self,
+        symbol: &str,
+        data: OptionSnapshot,
+        fetched_at: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        let json = serde_json::to_string(&data)?;
+
+        let symbol = symbol.to_uppercase();
+
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            "INSERT INTO option_snapshots (symbol, fetched_at, data) VALUES (?1, ?2, ?3)",
+            params![symbol, timestamp(fetched_at), json],
+        )?;
+        if let Some(retention) = self.retention {
+            transaction.execute(
+                "DELETE FROM option_snapshots WHERE symbol = ?1 AND fetched_at < ?2",
+                params![symbol, timestamp(fetched_at - retention)],
+            )?;
+        }
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    fn has_symbol(&self, symbol: &str) -> bool {
+        self.connection
+            .query_row(
+                "SELECT 1 FROM option_snapshots WHERE symbol = ?1 LIMIT 1",
+                params![symbol.to_uppercase()],
+                |_| Ok(()),
+            )
+            .optional()
+            .unwrap_or_else(|e| {
+                log::error!("{}", e);
+                None
+            })
+            .is_some()
+    }
+
+    fn option_chain(&self, symbol: &str) -> Option<OptionSnapshot> {
+        let (_, data) = self.latest_snapshot(symbol).unwrap_or_else(|e| {
+            log::error!("{}", e);
+            None
+        })?;
+
+        Self::parse_snapshot(&data)
+    }
+
+    fn fresh_option_chain(&self, symbol: &str, now: DateTime<Utc>) -> Option<OptionSnapshot> {
+        let (fetched_at, data) = self.latest_snapshot(symbol).unwrap_or_else(|e| {
+            log::error!("{}", e);
+            None
+        })?;
+
+        let fetched_at = DateTime::parse_from_rfc3339(&fetched_at).ok()?;
+        if now - fetched_at.with_timezone(&Utc) > self.ttl {
+            return None;
+        }
+
+        Self::parse_snapshot(&data)
+    }
+
+    fn option_chain_at(&self, symbol: &str, date: NaiveDate) -> Option<OptionSnapshot> {
+        let start_of_day = |date: NaiveDate| {
+            New_York
+                .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+                .earliest()
+                .map(|time| timestamp(time.with_timezone(&Utc)))
+        };
+        let start = start_of_day(date)?;
+        let end = start_of_day(date.succ_opt()?)?;
+
+        let data: Option<String> = self
+            .connection
+            .query_row(
+                "SELECT data FROM option_snapshots
+                 WHERE symbol = ?1 AND fetched_at >= ?2 AND fetched_at < ?3
+                 ORDER BY id DESC LIMIT 1",
+                params![symbol.to_uppercase(), start, end],
+                |row| row.get(0),
+            )
+            .optional()
+            .unwrap_or_else(|e| {
+                log::error!("{}", e);
+                None
+            });
+
+        Self::parse_snapshot(&data?)
+    }
+
+    fn symbols(&self) -> Vec<String> {
+        let symbols = self
+            .connection
+            .prepare("SELECT DISTINCT symbol FROM option_snapshots ORDER BY symbol")
+            .and_then(|mut statement| {
+                statement
+                    .query_map([], |row| row.get(0))?
+                    .collect::<Result<Vec<String>, _>>()
+            });
+
+        symbols.unwrap_or_else(|e| {
+            log::error!("{}", e);
+            Vec::new()
+        })
+    }
+
+    fn last_updated(&self, symbol: &str) -> Option<DateTime<Utc>> {
+        let (fetched_at, _) = self.latest_snapshot(symbol).unwrap_or_else(|e| {
+            log::error!("{}", e);
+            None
+        })?;
+
+        DateTime::parse_from_rfc3339(&fetched_at)
+            .ok()
+            .map(|time| time.with_timezone(&Utc))
+    }
+
+    fn delete_symbol(&mut self, symbol: &str) -> anyhow::Result<bool> {
+        let deleted = self.connection.execute(
+            "DELETE FROM option_snapshots WHERE symbol = ?1",
+            params![symbol.to_uppercase()],
+        )?;
+        Ok(deleted > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::OptionInfo;
+
+    #[test]
+    fn test_round_trip() {
+        let mut db = SqliteDb::open_in_memory().unwrap();
+
+        let mut older = OptionInfo::test();
+        older.strike = 100.0;
+        let mut newer = OptionInfo::test();
+        newer.strike = 105.0;
+
+        db.add_option_info("tst", vec![older]).unwrap();
+        db.add_option_info("TST", vec![newer.clone(), newer])
+            .unwrap();
+
+        assert!(db.has_symbol("TST"));
+        assert!(!db.has_symbol("OTHER"));
+
+        let chain = db.option_chain("TST").unwrap();
+        assert_eq!(2, chain.len());
+        assert_eq!(105.0, chain[0].strike);
+        assert_eq!("TST", chain[0].symbol);
+        assert!(db.option_chain("OTHER").is_none());
+    }
+
+    #[test]
+    fn test_symbols() {
+        let mut db = SqliteDb::open_in_memory().unwrap();
+        assert!(db.symbols().is_empty());
+
+        db.add_option_info("SPY", vec![OptionInfo::test()]).unwrap();
+        db.add_option_info("QQQ", vec![OptionInfo::test()]).unwrap();
+        db.add_option_info("spy", vec![OptionInfo::test()]).unwrap();
+
+        assert_eq!(vec!["QQQ", "SPY"], db.symbols());
+    }
+
+    #[test]
+    fn test_last_updated() {
+        let mut db = SqliteDb::open_in_memory().unwrap();
+        let fetched_at = Utc.with_ymd_and_hms(2021, 6, 1, 14, 0, 0).unwrap();
+        assert_eq!(None, db.last_updated("TST"));
+
+        db.add_option_info_at("TST", vec![OptionInfo::test()], fetched_at)
+            .unwrap();
+
+        assert_eq!(Some(fetched_at), db.last_updated("tst"));
+    }
+
+    #[test]
+    fn test_delete_symbol() {
+        let mut db = SqliteDb::open_in_memory().unwrap();
+        db.add_option_info("TST", vec![OptionInfo::test()]).unwrap();
+        db.add_option_info("TST", vec![OptionInfo::test()]).unwrap();
+        db.add_option_info("OTHER", vec![OptionInfo::test()])
+            .unwrap();
+
+        assert!(db.delete_symbol("tst").unwrap());
+        assert!(!db.delete_symbol("TST").unwrap());
+        assert_eq!(vec!["OTHER"], db.symbols());
+    }
+
+    #[test]
+    fn test_fresh_option_chain_expires_after_ttl() {
+        let mut db = SqliteDb::open_in_memory()
+            .unwrap()
+            .with_ttl(Duration::minutes(5));
+        let fetched_at = Utc::now();
+
+        db.add_option_info_at("TST", vec![OptionInfo::test()], fetched_at)
+            .unwrap();
+
+        assert!(db.fresh_option_chain("TST", fetched_at).is_some());
+        assert!(db
+            .fresh_option_chain("TST", fetched_at + Duration::minutes(6))
+            .is_none());
+    }
+
+    #[test]
+    fn test_persists_to_file() {
+        let path = "data/test_db.sqlite";
+        let _ = std::fs::remove_file(path);
+
+        {
+            let mut db = SqliteDb::open(path).unwrap();
+            db.add_option_info("TST", vec![OptionInfo::test()]).unwrap();
+        }
+
+        let db = SqliteDb::open(path).unwrap();
+        assert_eq!(vec!["TST"], db.symbols());
+        assert_eq!("TST", db.option_chain("TST").unwrap()[0].symbol);
+    }
+
+    #[test]
+    fn test_option_chain_at() {
+        let mut db = SqliteDb::open_in_memory().unwrap().with_retention(None);
+        // 11pm in New York is already the next day in UTC.
+        let monday_evening = Utc.with_ymd_and_hms(2021, 6, 8, 3, 0, 0).unwrap();
+        let tuesday = Utc.with_ymd_and_hms(2021, 6, 8, 15, 0, 0).unwrap();
+
+        let mut earlier = OptionInfo::test();
+        earlier.open_interest = 10;
+        let mut later = OptionInfo::test();
+        later.open_interest = 20;
+
+        db.add_option_info_at("TST", vec![earlier], monday_evening)
+            .unwrap();
+        db.add_option_info_at("TST", vec![later], tuesday).unwrap();
+
+        let date = NaiveDate::from_ymd_opt(2021, 6, 7).unwrap();
+        assert_eq!(
+            10,
+            db.option_chain_at("TST", date).unwrap()[0].open_interest
+        );
+        let date = NaiveDate::from_ymd_opt(2021, 6, 8).unwrap();
+        assert_eq!(
+            20,
+            db.option_chain_at("TST", date).unwrap()[0].open_interest
+        );
+        let date = NaiveDate::from_ymd_opt(2021, 6, 9).unwrap();
+        assert!(db.option_chain_at("TST", date).is_none());
+    }
+
+    #[test]
+    fn test_retention_prunes_old_snapshots() {
+        let mut db = SqliteDb::open_in_memory()
+            .unwrap()
+            .with_retention(Some(Duration::days(7)));
+        let start = Utc.with_ymd_and_hms(2021, 6, 1, 15, 0, 0).unwrap();
+
+        db.add_option_info_at("TST", vec![OptionInfo::test()], start)
+            .unwrap();
+        db.add_option_info_at("TST", vec![OptionInfo::test()], start + Duration::days(8))
+            .unwrap();
+
+        assert!(db.option_chain_at("TST", start.date_naive()).is_none());
+        assert!(db.option_chain("TST").is_some());
+    }
+}
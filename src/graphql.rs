@@ -1,22 +1,63 @@
-use std::sync::Arc;
-
 use crate::{
     analysis::{
-        gamma_exposure::{gamma_exposure, gamma_exposure_aggregate},
-        option_stats::option_stats,
+        charm_exposure::charm_exposure,
+        delta_exposure::delta_exposure,
+        gamma_exposure::{
+            gamma_exposure, gamma_exposure_aggregate, gamma_histogram, gamma_profile, parse_date,
+            GammaExposureOptions, CONTRACT_MULTIPLIER, DEFAULT_MAX_GAMMA,
+        },
+        gex_cache::GammaExposureCache,
+        indicators::{
+            bollinger_bands, moving_average, periods_per_year, realized_volatility, rsi,
+            DEFAULT_BOLLINGER_PERIOD, DEFAULT_BOLLINGER_STD_DEVS, DEFAULT_RSI_PERIOD,
+        },
+        option_stats::{
+            iv_skew, iv_term_structure, max_pain, most_active, most_active_by_open_interest,
+            oi_change, oi_weighted_iv, option_stats, put_call_ratio, put_call_volume_ratio,
+            DEFAULT_SKEW_OTM_PERCENT,
+        },
+        vanna_exposure::vanna_exposure,
+        vol_premium::vol_premium,
+    },
+    data_apis::{
+        tradier::{self, TradierError},
+        MarketDataProvider, Provider, SymbolError,
+    },
+    db::{self, SharedDb},
+    logging::FieldLogger,
+    math::bs::DEFAULT_RISK_FREE_RATE,
+    types::{
+        stats::{OpenInterestChange, OptionStats, StrikeStats, TermStructurePoint},
+        BollingerPoint, Dashboard, GammaExposureStats, GammaProfilePoint, IndicatorPoint, Ohlc,
+        OhlcInterval, OptionInfo, OptionType, Quote, QuoteResult, SymbolInfo,
     },
-    data_apis::tradier,
-    db::{self, FileDb},
-    types::{stats::StrikeStats, GammaExposureStats, Ohlc, OhlcInterval, Quote},
 };
-use async_graphql::{Context, EmptyMutation, EmptySubscription, Object};
-use tokio::sync::Mutex;
+use std::{collections::HashSet, time::Duration};
+
+use async_graphql::{
+    Context, EmptyMutation, EmptySubscription, ErrorExtensions, Object, Subscription,
+};
+use chrono::{DateTime, Utc};
+use futures::{future, stream, Stream};
+use reqwest::StatusCode;
+
+const DEFAULT_MOST_ACTIVE: usize = 10;
+const DEFAULT_VOL_PREMIUM_LOOKBACK_DAYS: i64 = 30;
 
-pub type Schema = async_graphql::Schema<Root, EmptyMutation, EmptySubscription>;
+pub type Schema = async_graphql::Schema<Root, MutationRoot, SubscriptionRoot>;
 
-pub fn schema(db: Arc<Mutex<FileDb>>) -> Schema {
-    async_graphql::Schema::build(Root, EmptyMutation, EmptySubscription)
+const DEFAULT_QUOTE_STREAM_INTERVAL_SECS: u64 = 5;
+
+pub fn schema(db: SharedDb) -> Schema {
+    schema_with_provider(db, Provider::from_env().client())
+}
+
+pub fn schema_with_provider(db: SharedDb, provider: Box<dyn MarketDataProvider>) -> Schema {
+    async_graphql::Schema::build(Root, MutationRoot, SubscriptionRoot)
         .data(db)
+        .data(provider)
+        .data(GammaExposureCache::new())
+        .extension(FieldLogger)
         .finish()
 }
 
@@ -24,90 +65,896 @@ pub struct Root;
 
 #[Object]
 impl Root {
-    async fn quote(&self, symbol: String) -> anyhow::Result<Quote> {
+    async fn quote(
+        &self,
+        context: &Context<'_>,
+        symbol: String,
+        provider: Option<Provider>,
+    ) -> async_graphql::Result<Quote> {
         log::info!("Querying quote");
-        let quote = tradier::get_quote(&symbol).await.map_err(log_error)?;
-        Ok(quote.into())
+        let quote = match provider {
+            Some(provider) => provider.client().get_quote(&symbol).await,
+            None => market_data(context)?.get_quote(&symbol).await,
+        }
+        .map_err(log_error)?;
+        Ok(quote)
+    }
+
+    // Fetches every symbol concurrently. A failed symbol reports its error in
+    // its own result instead of failing the whole batch.
+    async fn quotes(
+        &self,
+        context: &Context<'_>,
+        symbols: Vec<String>,
+        provider: Option<Provider>,
+    ) -> async_graphql::Result<Vec<QuoteResult>> {
+        log::info!("Querying quotes");
+        let client = provider.map(Provider::client);
+        let market_data = match &client {
+            Some(client) => client.as_ref(),
+            None => market_data(context)?,
+        };
+
+        let quotes =
+            future::join_all(symbols.iter().map(|symbol| market_data.get_quote(symbol))).await;
+        let results = symbols
+            .into_iter()
+            .zip(quotes)
+            .map(|(symbol, quote)| match quote {
+                Ok(quote) => QuoteResult {
+                    symbol,
+                    quote: Some(quote),
+                    error: None,
+                    error_code: None,
+                },
+                Err(e) => {
+                    log::error!("{}", e);
+                    QuoteResult {
+                        symbol,
+                        quote: None,
+                        error_code: Some(error_code(&e).to_string()),
+                        error: Some(e.to_string()),
+                    }
+                }
+            })
+            .collect();
+        Ok(results)
     }
 
     async fn ohlc(
         &self,
+        context: &Context<'_>,
         symbol: String,
         #[graphql(default_with = "default_interval()")] interval: OhlcInterval,
-    ) -> anyhow::Result<Vec<Ohlc>> {
+        provider: Option<Provider>,
+        start: Option<String>,
+        end: Option<String>,
+    ) -> async_graphql::Result<Vec<Ohlc>> {
         log::info!("Querying ohlc");
-        let ohlc = tradier::get_time_and_sales(&symbol, interval)
+        let ohlc = fetch_ohlc(context, &symbol, interval, provider, start, end)
             .await
             .map_err(log_error)?;
-        let result = ohlc.into_iter().map(|ts| (interval, ts).into()).collect();
-        Ok(result)
+        Ok(ohlc)
+    }
+
+    // Simple moving average of closes, null until the window fills.
+    #[allow(clippy::too_many_arguments)]
+    async fn moving_average(
+        &self,
+        context: &Context<'_>,
+        symbol: String,
+        #[graphql(default_with = "default_interval()")] interval: OhlcInterval,
+        window: usize,
+        provider: Option<Provider>,
+        start: Option<String>,
+        end: Option<String>,
+    ) -> async_graphql::Result<Vec<IndicatorPoint>> {
+        log::info!("Querying moving average");
+        let ohlc = fetch_ohlc(context, &symbol, interval, provider, start, end)
+            .await
+            .map_err(log_error)?;
+        Ok(indicator_points(&ohlc, moving_average(&ohlc, window)))
+    }
+
+    // Wilder's RSI of closes, null until `period` changes have been seen.
+    #[allow(clippy::too_many_arguments)]
+    async fn rsi(
+        &self,
+        context: &Context<'_>,
+        symbol: String,
+        #[graphql(default_with = "default_interval()")] interval: OhlcInterval,
+        #[graphql(default_with = "DEFAULT_RSI_PERIOD")] period: usize,
+        provider: Option<Provider>,
+        start: Option<String>,
+        end: Option<String>,
+    ) -> async_graphql::Result<Vec<IndicatorPoint>> {
+        log::info!("Querying rsi");
+        let ohlc = fetch_ohlc(context, &symbol, interval, provider, start, end)
+            .await
+            .map_err(log_error)?;
+        Ok(indicator_points(&ohlc, rsi(&ohlc, period)))
+    }
+
+    // Annualized volatility of close-to-close log returns. The annualization
+    // factor defaults to the number of bars in a year at this interval.
+    #[allow(clippy::too_many_arguments)]
+    async fn realized_volatility(
+        &self,
+        context: &Context<'_>,
+        symbol: String,
+        #[graphql(default_with = "default_history_interval()")] interval: OhlcInterval,
+        annualization: Option<f64>,
+        provider: Option<Provider>,
+        start: Option<String>,
+        end: Option<String>,
+    ) -> async_graphql::Result<f64> {
+        log::info!("Querying realized volatility");
+        let annualization = annualization
+            .or_else(|| periods_per_year(interval))
+            .ok_or_else(|| {
+                log_error(anyhow::anyhow!(
+                    "annualization is required for {} bars",
+                    interval
+                ))
+            })?;
+        let ohlc = fetch_ohlc(context, &symbol, interval, provider, start, end)
+            .await
+            .map_err(log_error)?;
+        if ohlc.len() < 3 {
+            return Err(log_error(anyhow::anyhow!(
+                "Not enough bars for {} to measure volatility",
+                symbol
+            )));
+        }
+        Ok(realized_volatility(&ohlc, annualization))
     }
 
-    async fn symbols(&self, context: &Context<'_>) -> anyhow::Result<Vec<String>> {
+    // Moving average of closes with bands `stdDevs` standard deviations away.
+    #[allow(clippy::too_many_arguments)]
+    async fn bollinger_bands(
+        &self,
+        context: &Context<'_>,
+        symbol: String,
+        #[graphql(default_with = "default_interval()")] interval: OhlcInterval,
+        #[graphql(default_with = "DEFAULT_BOLLINGER_PERIOD")] period: usize,
+        #[graphql(default_with = "DEFAULT_BOLLINGER_STD_DEVS")] std_devs: f64,
+        provider: Option<Provider>,
+        start: Option<String>,
+        end: Option<String>,
+    ) -> async_graphql::Result<Vec<BollingerPoint>> {
+        log::info!("Querying bollinger bands");
+        let ohlc = fetch_ohlc(context, &symbol, interval, provider, start, end)
+            .await
+            .map_err(log_error)?;
+        let points = ohlc
+            .iter()
+            .zip(bollinger_bands(&ohlc, period, std_devs))
+            .map(|(bar, (lower, middle, upper))| BollingerPoint {
+                time: bar.time.clone(),
+                lower,
+                middle,
+                upper,
+            })
+            .collect();
+        Ok(points)
+    }
+
+    async fn history(
+        &self,
+        context: &Context<'_>,
+        symbol: String,
+        start: String,
+        end: String,
+        #[graphql(default_with = "default_history_interval()")] interval: OhlcInterval,
+    ) -> async_graphql::Result<Vec<Ohlc>> {
+        log::info!("Querying history");
+        let history = fetch_ohlc(context, &symbol, interval, None, Some(start), Some(end))
+            .await
+            .map_err(log_error)?;
+        Ok(history)
+    }
+
+    // Always from Tradier: the other providers have no expirations endpoint.
+    async fn expirations(&self, symbol: String) -> async_graphql::Result<Vec<String>> {
+        log::info!("Querying expirations");
+        let expirations = tradier::get_option_expirations(&symbol)
+            .await
+            .map_err(log_error)?;
+        Ok(expirations)
+    }
+
+    async fn symbols(&self, context: &Context<'_>) -> async_graphql::Result<Vec<String>> {
         log::info!("Querying symbols");
         let db = context
-            .data::<Arc<Mutex<FileDb>>>()
+            .data::<SharedDb>()
             .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
         let db = db.lock().await;
         Ok(db.symbols())
     }
 
+    // Cached symbols with when each was last fetched, sorted by symbol.
+    async fn symbols_detailed(
+        &self,
+        context: &Context<'_>,
+    ) -> async_graphql::Result<Vec<SymbolInfo>> {
+        log::info!("Querying symbols detailed");
+        let db = context
+            .data::<SharedDb>()
+            .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
+        let db = db.lock().await;
+        let mut symbols = db.symbols();
+        symbols.sort();
+        Ok(symbols
+            .into_iter()
+            .map(|symbol| SymbolInfo {
+                last_updated: db.last_updated(&symbol).map(|time| time.to_rfc3339()),
+                has_options: db
+                    .option_chain(&symbol)
+                    .is_some_and(|chain| !chain.is_empty()),
+                symbol,
+            })
+            .collect())
+    }
+
+    // Strikes with less combined open interest than `minOpenInterest` are
+    // left out of every figure.
     async fn option_stats(
         &self,
         context: &Context<'_>,
         symbol: String,
-    ) -> anyhow::Result<Vec<StrikeStats>> {
+        #[graphql(default)] min_open_interest: u64,
+    ) -> async_graphql::Result<OptionStats> {
         log::info!("Querying option stats");
         let db = context
-            .data::<Arc<Mutex<FileDb>>>()
+            .data::<SharedDb>()
             .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
-        let option_chain = db::option_chain(&symbol, db.clone())
+        let option_chain = db::option_chain(&symbol, db.clone(), market_data(context)?)
             .await
             .map_err(log_error)?;
-        let stats = option_stats(&option_chain);
-        Ok(stats)
+        Ok(option_stats_summary(&option_chain, min_open_interest))
+    }
+
+    // Quote, option stats and default gamma exposure in one request. The
+    // chain and quote are fetched concurrently and the chain is shared.
+    async fn dashboard(
+        &self,
+        context: &Context<'_>,
+        symbol: String,
+    ) -> async_graphql::Result<Dashboard> {
+        log::info!("Querying dashboard");
+        let db = context
+            .data::<SharedDb>()
+            .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
+        let provider = market_data(context)?;
+        let (option_chain, quote) = future::join(
+            db::option_chain(&symbol, db.clone(), provider),
+            provider.get_quote(&symbol),
+        )
+        .await;
+        let option_chain = option_chain.map_err(log_error)?;
+        let quote = quote.map_err(log_error)?;
+
+        let options = GammaExposureOptions {
+            spot: quote.last,
+            ..Default::default()
+        };
+        let gamma_exposure = gamma_exposure(&symbol, &option_chain, &options).map_err(log_error)?;
+        let gamma_exposure = with_chain_timestamp(gamma_exposure, &symbol, db).await;
+        Ok(Dashboard {
+            quote,
+            option_stats: option_stats_summary(&option_chain, 0),
+            gamma_exposure,
+        })
+    }
+
+    async fn most_active(
+        &self,
+        context: &Context<'_>,
+        symbol: String,
+        #[graphql(default_with = "DEFAULT_MOST_ACTIVE")] n: usize,
+    ) -> async_graphql::Result<Vec<StrikeStats>> {
+        log::info!("Querying most active strikes");
+        let db = context
+            .data::<SharedDb>()
+            .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
+        let option_chain = db::option_chain(&symbol, db.clone(), market_data(context)?)
+            .await
+            .map_err(log_error)?;
+        Ok(most_active(&option_chain, n))
     }
 
+    async fn most_active_by_open_interest(
+        &self,
+        context: &Context<'_>,
+        symbol: String,
+        #[graphql(default_with = "DEFAULT_MOST_ACTIVE")] n: usize,
+    ) -> async_graphql::Result<Vec<StrikeStats>> {
+        log::info!("Querying most active strikes by open interest");
+        let db = context
+            .data::<SharedDb>()
+            .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
+        let option_chain = db::option_chain(&symbol, db.clone(), market_data(context)?)
+            .await
+            .map_err(log_error)?;
+        Ok(most_active_by_open_interest(&option_chain, n))
+    }
+
+    async fn iv_skew(
+        &self,
+        context: &Context<'_>,
+        symbol: String,
+        #[graphql(default_with = "DEFAULT_SKEW_OTM_PERCENT")] otm_percent: f64,
+        expiration: Option<String>,
+    ) -> async_graphql::Result<Option<f64>> {
+        log::info!("Querying iv skew");
+        let db = context
+            .data::<SharedDb>()
+            .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
+        let option_chain = db::option_chain(&symbol, db.clone(), market_data(context)?)
+            .await
+            .map_err(log_error)?;
+        let spot = spot_price(context, &symbol).await.map_err(log_error)?;
+
+        // Default to the nearest expiration so strikes from different dates
+        // aren't compared against each other.
+        let expiration = match expiration {
+            Some(e) => e,
+            None => match option_chain.iter().map(|o| &o.expiration_date).min() {
+                Some(e) => e.clone(),
+                None => return Ok(None),
+            },
+        };
+        let option_chain: Vec<_> = option_chain
+            .into_iter()
+            .filter(|o| o.expiration_date == expiration)
+            .collect();
+
+        Ok(iv_skew(&option_chain, spot, otm_percent))
+    }
+
+    async fn iv_term_structure(
+        &self,
+        context: &Context<'_>,
+        symbol: String,
+    ) -> async_graphql::Result<Vec<TermStructurePoint>> {
+        log::info!("Querying iv term structure");
+        let db = context
+            .data::<SharedDb>()
+            .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
+        let option_chain = db::option_chain(&symbol, db.clone(), market_data(context)?)
+            .await
+            .map_err(log_error)?;
+        let spot = spot_price(context, &symbol).await.map_err(log_error)?;
+        Ok(iv_term_structure(&option_chain, spot)
+            .into_iter()
+            .map(|(expiration_date, iv)| TermStructurePoint {
+                expiration_date,
+                iv,
+            })
+            .collect())
+    }
+
+    // Implied vol per expiration averaged across the whole chain, weighted by
+    // open interest.
+    async fn oi_weighted_iv(
+        &self,
+        context: &Context<'_>,
+        symbol: String,
+    ) -> async_graphql::Result<Vec<TermStructurePoint>> {
+        log::info!("Querying oi weighted iv");
+        let db = context
+            .data::<SharedDb>()
+            .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
+        let option_chain = db::option_chain(&symbol, db.clone(), market_data(context)?)
+            .await
+            .map_err(log_error)?;
+        Ok(oi_weighted_iv(&option_chain)
+            .into_iter()
+            .map(|(expiration_date, iv)| TermStructurePoint {
+                expiration_date,
+                iv,
+            })
+            .collect())
+    }
+
+    // Front-month ATM implied vol minus realized vol over the last
+    // `lookbackDays` of daily bars. Positive means options are rich.
+    async fn vol_premium(
+        &self,
+        context: &Context<'_>,
+        symbol: String,
+        #[graphql(default_with = "DEFAULT_VOL_PREMIUM_LOOKBACK_DAYS")] lookback_days: i64,
+    ) -> async_graphql::Result<f64> {
+        log::info!("Querying vol premium");
+        let db = context
+            .data::<SharedDb>()
+            .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
+        let option_chain = db::option_chain(&symbol, db.clone(), market_data(context)?)
+            .await
+            .map_err(log_error)?;
+        let end = Utc::now().date_naive();
+        let start = end - chrono::Duration::days(lookback_days);
+        let ohlc = market_data(context)?
+            .get_ohlc(&symbol, OhlcInterval::Daily, Some(start), Some(end))
+            .await
+            .map_err(log_error)?;
+        let premium = vol_premium(&symbol, &option_chain, &ohlc).map_err(log_error)?;
+        Ok(premium)
+    }
+
+    // Dates are `YYYY-MM-DD` in New York time.
+    async fn oi_change(
+        &self,
+        context: &Context<'_>,
+        symbol: String,
+        from: String,
+        to: String,
+    ) -> async_graphql::Result<Vec<OpenInterestChange>> {
+        log::info!("Querying open interest change");
+        let db = context
+            .data::<SharedDb>()
+            .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
+        let from = parse_date(&from).map_err(log_error)?;
+        let to = parse_date(&to).map_err(log_error)?;
+        let from_chain = db::option_chain_at(&symbol, from, db.clone())
+            .await
+            .map_err(log_error)?;
+        let to_chain = db::option_chain_at(&symbol, to, db.clone())
+            .await
+            .map_err(log_error)?;
+        Ok(oi_change(&from_chain, &to_chain))
+    }
+
+    #[allow(clippy::too_many_arguments)]
     async fn gamma_exposure(
         &self,
         context: &Context<'_>,
         symbol: String,
-    ) -> anyhow::Result<GammaExposureStats> {
+        #[graphql(default_with = "DEFAULT_MAX_GAMMA")] max_gamma: f64,
+        #[graphql(default)] dollar: bool,
+        expiration: Option<String>,
+        #[graphql(default)] per_contract: bool,
+        max_missing_greeks: Option<f64>,
+        option_type: Option<OptionType>,
+        #[graphql(default)] normalize_strike_spacing: bool,
+        #[graphql(default)] min_open_interest: u64,
+    ) -> async_graphql::Result<GammaExposureStats> {
         log::info!("Querying gamma exposure");
         let db = context
-            .data::<Arc<Mutex<FileDb>>>()
+            .data::<SharedDb>()
             .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
-        let option_chain = db::option_chain(&symbol, db.clone())
+        let options = GammaExposureOptions {
+            max_gamma,
+            dollar,
+            expiration,
+            contract_multiplier: contract_multiplier(per_contract),
+            max_missing_greeks,
+            option_type,
+            normalize_strike_spacing,
+            min_open_interest,
+            ..Default::default()
+        };
+        let gex = gamma_exposure_stats(&symbol, db, market_data(context)?, options)
             .await
             .map_err(log_error)?;
-        let gex = gamma_exposure(&symbol, &option_chain).unwrap();
         Ok(gex)
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn gamma_exposure_aggregate(
         &self,
         context: &Context<'_>,
         symbol: String,
-    ) -> anyhow::Result<GammaExposureStats> {
+        #[graphql(default_with = "DEFAULT_MAX_GAMMA")] max_gamma: f64,
+        #[graphql(default_with = "DEFAULT_RISK_FREE_RATE")] risk_free_rate: f64,
+        #[graphql(default)] american: bool,
+        expiration: Option<String>,
+        min_dte: Option<i64>,
+        max_dte: Option<i64>,
+        #[graphql(default)] per_contract: bool,
+        spot_band: Option<f64>,
+    ) -> async_graphql::Result<GammaExposureStats> {
         log::info!("Querying gamma exposure aggregate");
         let db = context
-            .data::<Arc<Mutex<FileDb>>>()
+            .data::<SharedDb>()
             .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
-        let option_chain = db::option_chain(&symbol, db.clone())
+        let option_chain = db::option_chain(&symbol, db.clone(), market_data(context)?)
             .await
             .map_err(log_error)?;
-        let gex_agg = gamma_exposure_aggregate(&symbol, &option_chain).unwrap();
-        Ok(gex_agg)
+        let spot = match spot_band {
+            Some(_) => Some(spot_price(context, &symbol).await.map_err(log_error)?),
+            None => None,
+        };
+        let options = GammaExposureOptions {
+            max_gamma,
+            risk_free_rate,
+            american,
+            expiration,
+            min_dte,
+            max_dte,
+            contract_multiplier: contract_multiplier(per_contract),
+            spot,
+            spot_band,
+            ..Default::default()
+        };
+        let cache = context
+            .data::<GammaExposureCache>()
+            .map_err(|_| anyhow::anyhow!("Failed to load gamma exposure cache"))?;
+        let gex_agg = cache
+            .get_or_compute(&symbol, &option_chain, &options, || {
+                gamma_exposure_aggregate(&symbol, &option_chain, &options)
+            })
+            .map_err(log_error)?;
+        Ok(with_chain_timestamp(gex_agg, &symbol, db).await)
+    }
+
+    async fn gamma_histogram(
+        &self,
+        context: &Context<'_>,
+        symbol: String,
+        bucket_width: f64,
+    ) -> async_graphql::Result<Vec<GammaProfilePoint>> {
+        log::info!("Querying gamma histogram");
+        let db = context
+            .data::<SharedDb>()
+            .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
+        let option_chain = db::option_chain(&symbol, db.clone(), market_data(context)?)
+            .await
+            .map_err(log_error)?;
+        let gex = gamma_exposure(&symbol, &option_chain, &Default::default()).map_err(log_error)?;
+        Ok(gamma_histogram(&gex, bucket_width)
+            .into_iter()
+            .map(|(price, gamma_exposure)| GammaProfilePoint {
+                price,
+                gamma_exposure,
+            })
+            .collect())
+    }
+
+    async fn delta_exposure(
+        &self,
+        context: &Context<'_>,
+        symbol: String,
+        #[graphql(default_with = "DEFAULT_RISK_FREE_RATE")] risk_free_rate: f64,
+        expiration: Option<String>,
+    ) -> async_graphql::Result<GammaExposureStats> {
+        log::info!("Querying delta exposure");
+        let db = context
+            .data::<SharedDb>()
+            .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
+        let option_chain = db::option_chain(&symbol, db.clone(), market_data(context)?)
+            .await
+            .map_err(log_error)?;
+        let spot = spot_price(context, &symbol).await.map_err(log_error)?;
+        let options = GammaExposureOptions {
+            risk_free_rate,
+            expiration,
+            ..Default::default()
+        };
+        let dex = delta_exposure(&symbol, &option_chain, spot, &options).map_err(log_error)?;
+        Ok(dex)
+    }
+
+    async fn vanna_exposure(
+        &self,
+        context: &Context<'_>,
+        symbol: String,
+        #[graphql(default_with = "DEFAULT_RISK_FREE_RATE")] risk_free_rate: f64,
+        expiration: Option<String>,
+    ) -> async_graphql::Result<GammaExposureStats> {
+        log::info!("Querying vanna exposure");
+        let db = context
+            .data::<SharedDb>()
+            .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
+        let option_chain = db::option_chain(&symbol, db.clone(), market_data(context)?)
+            .await
+            .map_err(log_error)?;
+        let spot = spot_price(context, &symbol).await.map_err(log_error)?;
+        let options = GammaExposureOptions {
+            risk_free_rate,
+            expiration,
+            ..Default::default()
+        };
+        let vex = vanna_exposure(&symbol, &option_chain, spot, &options).map_err(log_error)?;
+        Ok(vex)
+    }
+
+    async fn charm_exposure(
+        &self,
+        context: &Context<'_>,
+        symbol: String,
+        #[graphql(default_with = "DEFAULT_RISK_FREE_RATE")] risk_free_rate: f64,
+        expiration: Option<String>,
+    ) -> async_graphql::Result<GammaExposureStats> {
+        log::info!("Querying charm exposure");
+        let db = context
+            .data::<SharedDb>()
+            .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
+        let option_chain = db::option_chain(&symbol, db.clone(), market_data(context)?)
+            .await
+            .map_err(log_error)?;
+        let spot = spot_price(context, &symbol).await.map_err(log_error)?;
+        let options = GammaExposureOptions {
+            risk_free_rate,
+            expiration,
+            ..Default::default()
+        };
+        let cex = charm_exposure(&symbol, &option_chain, spot, &options).map_err(log_error)?;
+        Ok(cex)
+    }
+
+    async fn gamma_profile(
+        &self,
+        context: &Context<'_>,
+        symbol: String,
+        lo: f64,
+        hi: f64,
+        #[graphql(default_with = "1.0")] step: f64,
+        #[graphql(default_with = "DEFAULT_RISK_FREE_RATE")] risk_free_rate: f64,
+    ) -> async_graphql::Result<Vec<GammaProfilePoint>> {
+        log::info!("Querying gamma profile");
+        let db = context
+            .data::<SharedDb>()
+            .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
+        let option_chain = db::option_chain(&symbol, db.clone(), market_data(context)?)
+            .await
+            .map_err(log_error)?;
+        let options = GammaExposureOptions {
+            risk_free_rate,
+            ..Default::default()
+        };
+        let profile = gamma_profile(&option_chain, lo, hi, step, &options).map_err(log_error)?;
+        Ok(profile
+            .into_iter()
+            .map(|(price, gamma_exposure)| GammaProfilePoint {
+                price,
+                gamma_exposure,
+            })
+            .collect())
+    }
+}
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    // Downloads a new option chain for the symbol and returns when it was
+    // fetched.
+    async fn refresh(
+        &self,
+        context: &Context<'_>,
+        symbol: String,
+    ) -> async_graphql::Result<String> {
+        log::info!("Refreshing {}", symbol);
+        let db = context
+            .data::<SharedDb>()
+            .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
+        let fetched_at = refresh_option_chain(&symbol, db, market_data(context)?)
+            .await
+            .map_err(log_error)?;
+        Ok(fetched_at.to_rfc3339())
+    }
+
+    // Removes the symbol from the db. Returns whether it was cached.
+    async fn evict(&self, context: &Context<'_>, symbol: String) -> async_graphql::Result<bool> {
+        log::info!("Evicting {}", symbol);
+        let db = context
+            .data::<SharedDb>()
+            .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
+        let removed = db.lock().await.delete_symbol(&symbol).map_err(log_error)?;
+        Ok(removed)
+    }
+}
+
+pub struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    // Polls the market data provider, yielding a quote right away and then
+    // once per interval.
+    async fn quote_stream<'ctx>(
+        &self,
+        context: &'ctx Context<'_>,
+        symbol: String,
+        #[graphql(default_with = "DEFAULT_QUOTE_STREAM_INTERVAL_SECS")] interval_secs: u64,
+    ) -> async_graphql::Result<impl Stream<Item = async_graphql::Result<Quote>> + 'ctx> {
+        log::info!("Streaming quotes for {}", symbol);
+        let provider = market_data(context)?;
+        let interval = tokio::time::interval(Duration::from_secs(interval_secs.max(1)));
+
+        Ok(stream::unfold(
+            (interval, symbol),
+            move |(mut interval, symbol)| async move {
+                interval.tick().await;
+                let quote = provider.get_quote(&symbol).await.map_err(log_error);
+                Some((quote, (interval, symbol)))
+            },
+        ))
+    }
+}
+
+async fn fetch_ohlc(
+    context: &Context<'_>,
+    symbol: &str,
+    interval: OhlcInterval,
+    provider: Option<Provider>,
+    start: Option<String>,
+    end: Option<String>,
+) -> anyhow::Result<Vec<Ohlc>> {
+    let start = start.map(|date| parse_date(&date)).transpose()?;
+    let end = end.map(|date| parse_date(&date)).transpose()?;
+    if let (Some(start), Some(end)) = (start, end) {
+        if start > end {
+            anyhow::bail!("start {} is after end {}", start, end);
+        }
+    }
+
+    match provider {
+        Some(provider) => {
+            provider
+                .client()
+                .get_ohlc(symbol, interval, start, end)
+                .await
+        }
+        None => {
+            market_data(context)?
+                .get_ohlc(symbol, interval, start, end)
+                .await
+        }
     }
 }
 
+fn indicator_points(ohlc: &[Ohlc], values: Vec<Option<f64>>) -> Vec<IndicatorPoint> {
+    ohlc.iter()
+        .zip(values)
+        .map(|(bar, value)| IndicatorPoint {
+            time: bar.time.clone(),
+            value,
+        })
+        .collect()
+}
+
 fn default_interval() -> OhlcInterval {
     OhlcInterval::FiveMinute
 }
 
-fn log_error(error: anyhow::Error) -> anyhow::Error {
+fn default_history_interval() -> OhlcInterval {
+    OhlcInterval::Daily
+}
+
+fn option_stats_summary(option_chain: &[OptionInfo], min_open_interest: u64) -> OptionStats {
+    let strikes = option_stats(option_chain, min_open_interest);
+    let kept_strikes: HashSet<u64> = strikes.iter().map(|s| s.strike.to_bits()).collect();
+    let kept_options: Vec<OptionInfo> = option_chain
+        .iter()
+        .filter(|o| kept_strikes.contains(&o.strike.to_bits()))
+        .cloned()
+        .collect();
+
+    // Every figure is over the same strikes, so thin strikes drop out of the
+    // ratios too.
+    OptionStats {
+        max_pain: max_pain(&kept_options),
+        strikes,
+        put_call_ratio: put_call_ratio(&kept_options),
+        put_call_volume_ratio: put_call_volume_ratio(&kept_options),
+    }
+}
+
+/// Gamma exposure for the cached chain, priced against the provider's spot.
+/// Backs both the `gammaExposure` query and the REST route. A failed quote
+/// only leaves `spot_price` empty; the cached chain is still served.
+pub async fn gamma_exposure_stats(
+    symbol: &str,
+    db: &SharedDb,
+    provider: &dyn MarketDataProvider,
+    options: GammaExposureOptions,
+) -> anyhow::Result<GammaExposureStats> {
+    let option_chain = db::option_chain(symbol, db.clone(), provider).await?;
+    // Dollar exposure is scaled by spot, so only plain exposure can go
+    // without a quote.
+    let spot = match provider.get_quote(symbol).await {
+        Ok(quote) => quote.last,
+        Err(e) if options.dollar => return Err(e),
+        Err(e) => {
+            log::error!("Failed to get spot price for {}: {}", symbol, e);
+            None
+        }
+    };
+    if options.dollar && spot.is_none() {
+        anyhow::bail!("No spot price for {}", symbol);
+    }
+    let options = GammaExposureOptions { spot, ..options };
+    let stats = gamma_exposure(symbol, &option_chain, &options)?;
+    Ok(with_chain_timestamp(stats, symbol, db).await)
+}
+
+// Stamps the stats with when the db fetched the chain they were built from.
+async fn with_chain_timestamp(
+    mut stats: GammaExposureStats,
+    symbol: &str,
+    db: &SharedDb,
+) -> GammaExposureStats {
+    stats.chain_timestamp = db
+        .lock()
+        .await
+        .last_updated(symbol)
+        .map(|fetched_at| fetched_at.to_rfc3339());
+    stats
+}
+
+/// Fetches a fresh chain from the provider and stores it, returning when it
+/// was fetched.
+pub async fn refresh_option_chain(
+    symbol: &str,
+    db: &SharedDb,
+    provider: &dyn MarketDataProvider,
+) -> anyhow::Result<DateTime<Utc>> {
+    let option_chain = provider.get_option_chain(&symbol.to_uppercase()).await?;
+    let fetched_at = Utc::now();
+    db.lock()
+        .await
+        .add_option_info_at(symbol, option_chain, fetched_at)?;
+    Ok(fetched_at)
+}
+
+// Per-contract exposure is the pre-multiplier behaviour, kept for old clients.
+fn contract_multiplier(per_contract: bool) -> f64 {
+    if per_contract {
+        1.0
+    } else {
+        CONTRACT_MULTIPLIER
+    }
+}
+
+fn market_data<'a>(context: &'a Context<'_>) -> anyhow::Result<&'a dyn MarketDataProvider> {
+    context
+        .data::<Box<dyn MarketDataProvider>>()
+        .map(|provider| provider.as_ref())
+        .map_err(|_| anyhow::anyhow!("Failed to load market data provider"))
+}
+
+async fn spot_price(context: &Context<'_>, symbol: &str) -> anyhow::Result<f64> {
+    market_data(context)?
+        .get_quote(symbol)
+        .await?
+        .last
+        .ok_or_else(|| anyhow::anyhow!("No last price for {}", symbol))
+}
+
+// Tags the error with a `code` extension so clients can tell a bad symbol or
+// an upstream outage apart from a bug.
+fn log_error(error: anyhow::Error) -> async_graphql::Error {
     log::error!("{}", error);
-    error
+    let code = error_code(&error);
+    async_graphql::Error::new(error.to_string()).extend_with(|_, e| e.set("code", code))
+}
+
+pub fn error_code(error: &anyhow::Error) -> &'static str {
+    if error.downcast_ref::<SymbolError>().is_some() {
+        return "BAD_SYMBOL";
+    }
+
+    if let Some(error) = error.downcast_ref::<TradierError>() {
+        return match error {
+            TradierError::Unauthorized(_) => "UNAUTHORIZED",
+            TradierError::RateLimited(_) => "RATE_LIMITED",
+            TradierError::Timeout | TradierError::Server(..) => "UPSTREAM_UNAVAILABLE",
+            TradierError::Status(..) => "UPSTREAM_ERROR",
+        };
+    }
+
+    if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+        return match error.status() {
+            Some(StatusCode::UNAUTHORIZED) | Some(StatusCode::FORBIDDEN) => "UNAUTHORIZED",
+            Some(StatusCode::NOT_FOUND) => "BAD_SYMBOL",
+            Some(StatusCode::TOO_MANY_REQUESTS) => "RATE_LIMITED",
+            Some(status) if status.is_client_error() => "UPSTREAM_ERROR",
+            _ => "UPSTREAM_UNAVAILABLE",
+        };
+    }
+
+    "INTERNAL"
 }
 
 /*
@@ -116,9 +963,10 @@ fn log_error(error: anyhow::Error) -> anyhow::Error {
 
 pub type TdaSchema = async_graphql::Schema<TdaRoot, EmptyMutation, EmptySubscription>;
 
-pub fn tda_schema(db: Arc<Mutex<FileDb>>) -> TdaSchema {
+pub fn tda_schema(db: SharedDb) -> TdaSchema {
     async_graphql::Schema::build(TdaRoot, EmptyMutation, EmptySubscription)
         .data(db)
+        .extension(FieldLogger)
         .finish()
 }
 
@@ -126,7 +974,7 @@ pub struct TdaRoot;
 
 #[Object]
 impl TdaRoot {
-    async fn quote(&self, symbol: String) -> anyhow::Result<Quote> {
+    async fn quote(&self, symbol: String) -> async_graphql::Result<Quote> {
         log::info!("Querying quote");
         let quote = tradier::get_quote(&symbol).await.map_err(log_error)?;
         Ok(quote.into())
@@ -136,19 +984,19 @@ impl TdaRoot {
         &self,
         symbol: String,
         #[graphql(default_with = "default_interval()")] interval: OhlcInterval,
-    ) -> anyhow::Result<Vec<Ohlc>> {
+    ) -> async_graphql::Result<Vec<Ohlc>> {
         log::info!("Querying ohlc");
-        let ohlc = tradier::get_time_and_sales(&symbol, interval)
+        let ohlc = tradier::get_time_and_sales(&symbol, interval, None, None)
             .await
             .map_err(log_error)?;
         let result = ohlc.into_iter().map(|ts| (interval, ts).into()).collect();
         Ok(result)
     }
 
-    async fn symbols(&self, context: &Context<'_>) -> anyhow::Result<Vec<String>> {
+    async fn symbols(&self, context: &Context<'_>) -> async_graphql::Result<Vec<String>> {
         log::info!("Querying symbols");
         let db = context
-            .data::<Arc<Mutex<FileDb>>>()
+            .data::<SharedDb>()
             .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
         let db = db.lock().await;
         Ok(db.symbols())
@@ -158,15 +1006,15 @@ impl TdaRoot {
         &self,
         context: &Context<'_>,
         symbol: String,
-    ) -> anyhow::Result<Vec<StrikeStats>> {
+    ) -> async_graphql::Result<Vec<StrikeStats>> {
         log::info!("Querying option stats");
         let db = context
-            .data::<Arc<Mutex<FileDb>>>()
+            .data::<SharedDb>()
             .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
-        let option_chain = db::option_chain(&symbol, db.clone())
+        let option_chain = db::option_chain(&symbol, db.clone(), &tradier::Tradier)
             .await
             .map_err(log_error)?;
-        let stats = option_stats(&option_chain);
+        let stats = option_stats(&option_chain, 0);
         Ok(stats)
     }
 
@@ -174,15 +1022,15 @@ impl TdaRoot {
         &self,
         context: &Context<'_>,
         symbol: String,
-    ) -> anyhow::Result<GammaExposureStats> {
+    ) -> async_graphql::Result<GammaExposureStats> {
         log::info!("Querying gamma exposure");
         let db = context
-            .data::<Arc<Mutex<FileDb>>>()
+            .data::<SharedDb>()
             .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
-        let option_chain = db::option_chain(&symbol, db.clone())
+        let option_chain = db::option_chain(&symbol, db.clone(), &tradier::Tradier)
             .await
             .map_err(log_error)?;
-        let gex = gamma_exposure(&symbol, &option_chain).unwrap();
+        let gex = gamma_exposure(&symbol, &option_chain, &Default::default()).map_err(log_error)?;
         Ok(gex)
     }
 
@@ -190,15 +1038,518 @@ impl TdaRoot {
         &self,
         context: &Context<'_>,
         symbol: String,
-    ) -> anyhow::Result<GammaExposureStats> {
+    ) -> async_graphql::Result<GammaExposureStats> {
         log::info!("Querying gamma exposure aggregate");
         let db = context
-            .data::<Arc<Mutex<FileDb>>>()
+            .data::<SharedDb>()
             .map_err(|_| anyhow::anyhow!("Failed to load db"))?;
-        let option_chain = db::option_chain(&symbol, db.clone())
+        let option_chain = db::option_chain(&symbol, db.clone(), &tradier::Tradier)
             .await
             .map_err(log_error)?;
-        let gex_agg = gamma_exposure_aggregate(&symbol, &option_chain).unwrap();
+        let gex_agg = gamma_exposure_aggregate(&symbol, &option_chain, &Default::default())
+            .map_err(log_error)?;
         Ok(gex_agg)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data_apis::WithOptionChains,
+        db::{Database, FileDb},
+    };
+    use async_trait::async_trait;
+    use chrono::NaiveDate;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    struct MockProvider;
+
+    #[async_trait]
+    impl MarketDataProvider for MockProvider {
+        async fn get_quote(&self, symbol: &str) -> anyhow::Result<Quote> {
+            if symbol == "BAD" {
+                return Err(SymbolError::Unknown(symbol.to_string()).into());
+            }
+
+            Ok(Quote {
+                symbol: symbol.to_string(),
+                last: Some(101.5),
+                change: Some(1.5),
+                volume: 1000,
+                open: Some(100.0),
+                high: Some(102.0),
+                low: Some(99.0),
+                close: Some(101.5),
+            })
+        }
+
+        async fn get_ohlc(
+            &self,
+            _symbol: &str,
+            interval: OhlcInterval,
+            start: Option<NaiveDate>,
+            _end: Option<NaiveDate>,
+        ) -> anyhow::Result<Vec<Ohlc>> {
+            let date = start.unwrap_or_else(|| NaiveDate::from_ymd_opt(2021, 6, 1).unwrap());
+            Ok(vec![Ohlc {
+                interval,
+                time: format!("{}T09:30:00", date),
+                price: 101.0,
+                open: 100.0,
+                high: 102.0,
+                low: 99.0,
+                close: 101.0,
+                volume: 500,
+                vwap: None,
+            }])
+        }
+
+        async fn get_option_chain(&self, symbol: &str) -> anyhow::Result<Vec<OptionInfo>> {
+            let mut option = OptionInfo::test();
+            option.symbol = symbol.to_string();
+            Ok(vec![option])
+        }
+    }
+
+    fn mock_schema() -> Schema {
+        mock_schema_with_db(Arc::new(Mutex::new(FileDb::new("data/test_graphql_db.gz"))))
+    }
+
+    fn mock_schema_with_db(db: SharedDb) -> Schema {
+        schema_with_provider(db, Box::new(MockProvider))
+    }
+
+    #[tokio::test]
+    async fn test_quote_uses_context_provider() {
+        let response = mock_schema()
+            .execute(r#"{ quote(symbol: "SPY") { symbol last change volume } }"#)
+            .await;
+
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        assert_eq!("SPY", data["quote"]["symbol"]);
+        assert_eq!(101.5, data["quote"]["last"]);
+        assert_eq!(1000, data["quote"]["volume"]);
+    }
+
+    #[tokio::test]
+    async fn test_ohlc_uses_context_provider() {
+        let response = mock_schema()
+            .execute(r#"{ ohlc(symbol: "SPY", interval: daily) { interval time close volume } }"#)
+            .await;
+
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        assert_eq!(1, data["ohlc"].as_array().unwrap().len());
+        assert_eq!("daily", data["ohlc"][0]["interval"]);
+        assert_eq!(101.0, data["ohlc"][0]["close"]);
+    }
+
+    #[tokio::test]
+    async fn test_moving_average() {
+        let response = mock_schema()
+            .execute(
+                r#"{
+                    one: movingAverage(symbol: "SPY", window: 1) { time value }
+                    two: movingAverage(symbol: "SPY", window: 2) { time value }
+                }"#,
+            )
+            .await;
+
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        assert_eq!("2021-06-01T09:30:00", data["one"][0]["time"]);
+        assert_eq!(101.0, data["one"][0]["value"]);
+        assert!(data["two"][0]["value"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_rsi() {
+        let response = mock_schema()
+            .execute(r#"{ rsi(symbol: "SPY", period: 14) { time value } }"#)
+            .await;
+
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        assert_eq!(1, data["rsi"].as_array().unwrap().len());
+        assert!(data["rsi"][0]["value"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_bollinger_bands() {
+        let response = mock_schema()
+            .execute(r#"{ bollingerBands(symbol: "SPY", period: 1) { lower middle upper } }"#)
+            .await;
+
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        let band = &data["bollingerBands"][0];
+        assert_eq!(101.0, band["lower"]);
+        assert_eq!(101.0, band["middle"]);
+        assert_eq!(101.0, band["upper"]);
+    }
+
+    #[tokio::test]
+    async fn test_symbols_detailed() {
+        let mut db = FileDb::new("data/test_symbols_detailed_db.gz");
+        let stale = Utc::now() - chrono::Duration::days(1);
+        let fresh = Utc::now();
+        db.add_option_info_at("SPY", vec![OptionInfo::test()], fresh)
+            .unwrap();
+        db.add_option_info_at("QQQ", vec![], stale).unwrap();
+        let schema = mock_schema_with_db(Arc::new(Mutex::new(db)));
+
+        let response = schema
+            .execute("{ symbolsDetailed { symbol lastUpdated hasOptions } }")
+            .await;
+
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        let symbols = &data["symbolsDetailed"];
+        assert_eq!("QQQ", symbols[0]["symbol"]);
+        assert_eq!(stale.to_rfc3339(), symbols[0]["lastUpdated"]);
+        assert_eq!(false, symbols[0]["hasOptions"]);
+        assert_eq!("SPY", symbols[1]["symbol"]);
+        assert_eq!(fresh.to_rfc3339(), symbols[1]["lastUpdated"]);
+        assert_eq!(true, symbols[1]["hasOptions"]);
+    }
+
+    #[tokio::test]
+    async fn test_dashboard() {
+        let mut db = FileDb::new("data/test_dashboard_db.gz");
+        let mut option = OptionInfo::test();
+        option.strike = 100.0;
+        db.add_option_info("TST", vec![option]).unwrap();
+        let schema = mock_schema_with_db(Arc::new(Mutex::new(db)));
+
+        let response = schema
+            .execute(
+                r#"{
+                    dashboard(symbol: "TST") {
+                        quote { symbol last }
+                        optionStats { maxPain strikes { strike } }
+                        gammaExposure { symbol spotPrice chainTimestamp prices { strike } }
+                    }
+                }"#,
+            )
+            .await;
+
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        let dashboard = &data["dashboard"];
+        assert_eq!(101.5, dashboard["quote"]["last"]);
+        assert_eq!(100.0, dashboard["optionStats"]["strikes"][0]["strike"]);
+        assert_eq!("TST", dashboard["gammaExposure"]["symbol"]);
+        assert_eq!(101.5, dashboard["gammaExposure"]["spotPrice"]);
+        assert_eq!("100", dashboard["gammaExposure"]["prices"][0]["strike"]);
+        assert!(dashboard["gammaExposure"]["chainTimestamp"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_evict() {
+        let db: SharedDb = Arc::new(Mutex::new(FileDb::new("data/test_evict_db.gz")));
+        db.lock()
+            .await
+            .add_option_info("TST", vec![OptionInfo::test()])
+            .unwrap();
+        let schema = mock_schema_with_db(db.clone());
+
+        let response = schema.execute(r#"mutation { evict(symbol: "TST") }"#).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(true, response.data.into_json().unwrap()["evict"]);
+        assert!(!db.lock().await.has_symbol("TST"));
+
+        let response = schema.execute(r#"mutation { evict(symbol: "TST") }"#).await;
+        assert_eq!(false, response.data.into_json().unwrap()["evict"]);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_stores_option_chain() {
+        let db: SharedDb = Arc::new(Mutex::new(FileDb::new("data/test_refresh_db.gz")));
+        let schema = mock_schema_with_db(db.clone());
+        assert!(!db.lock().await.has_symbol("TST"));
+
+        let response = schema
+            .execute(r#"mutation { refresh(symbol: "tst") }"#)
+            .await;
+
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        let fetched_at = data["refresh"].as_str().unwrap();
+        let fetched_at = chrono::DateTime::parse_from_rfc3339(fetched_at).unwrap();
+
+        let db = db.lock().await;
+        assert_eq!("TST", db.option_chain("TST").unwrap()[0].symbol);
+        assert!(db
+            .fresh_option_chain("TST", fetched_at.with_timezone(&Utc))
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_quote_stream_yields_quotes() {
+        use futures::StreamExt;
+
+        let schema = mock_schema();
+        let responses: Vec<_> = schema
+            .execute_stream(
+                r#"subscription { quoteStream(symbol: "SPY", intervalSecs: 1) { symbol last } }"#,
+            )
+            .take(2)
+            .collect()
+            .await;
+
+        assert_eq!(2, responses.len());
+        for response in responses {
+            assert!(response.errors.is_empty(), "{:?}", response.errors);
+            let data = response.data.into_json().unwrap();
+            assert_eq!("SPY", data["quoteStream"]["symbol"]);
+            assert_eq!(101.5, data["quoteStream"]["last"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_symbol_returns_error_code() {
+        let response = mock_schema()
+            .execute(r#"{ quote(symbol: "BAD") { symbol last } }"#)
+            .await;
+
+        assert_eq!(1, response.errors.len());
+        let response = serde_json::to_value(&response).unwrap();
+        assert_eq!(serde_json::Value::Null, response["data"]);
+        assert_eq!("Unknown symbol: BAD", response["errors"][0]["message"]);
+        assert_eq!("BAD_SYMBOL", response["errors"][0]["extensions"]["code"]);
+    }
+
+    #[test]
+    fn test_error_code() {
+        let code = |error: anyhow::Error| error_code(&error);
+
+        assert_eq!(
+            "BAD_SYMBOL",
+            code(SymbolError::NoOptions("TST".to_string()).into())
+        );
+        assert_eq!(
+            "UNAUTHORIZED",
+            code(TradierError::Unauthorized(String::new()).into())
+        );
+        assert_eq!(
+            "RATE_LIMITED",
+            code(TradierError::RateLimited(String::new()).into())
+        );
+        assert_eq!("UPSTREAM_UNAVAILABLE", code(TradierError::Timeout.into()));
+        assert_eq!(
+            "UPSTREAM_UNAVAILABLE",
+            code(TradierError::Server(StatusCode::BAD_GATEWAY, String::new()).into())
+        );
+        assert_eq!("INTERNAL", code(anyhow::anyhow!("Failed to load db")));
+    }
+
+    #[tokio::test]
+    async fn test_quotes_reports_per_symbol_errors() {
+        let response = mock_schema()
+            .execute(
+                r#"{ quotes(symbols: ["SPY", "BAD"]) { symbol quote { last } error errorCode } }"#,
+            )
+            .await;
+
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        let quotes = data["quotes"].as_array().unwrap();
+        assert_eq!(2, quotes.len());
+
+        assert_eq!("SPY", quotes[0]["symbol"]);
+        assert_eq!(101.5, quotes[0]["quote"]["last"]);
+        assert_eq!(serde_json::Value::Null, quotes[0]["error"]);
+
+        assert_eq!("BAD", quotes[1]["symbol"]);
+        assert_eq!(serde_json::Value::Null, quotes[1]["quote"]);
+        assert_eq!("Unknown symbol: BAD", quotes[1]["error"]);
+        assert_eq!("BAD_SYMBOL", quotes[1]["errorCode"]);
+    }
+
+    #[tokio::test]
+    async fn test_ohlc_date_range() {
+        let schema = mock_schema();
+
+        let response = schema
+            .execute(r#"{ ohlc(symbol: "SPY", start: "2021-05-03", end: "2021-05-07") { time } }"#)
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        assert_eq!("2021-05-03T09:30:00", data["ohlc"][0]["time"]);
+
+        let response = schema
+            .execute(r#"{ ohlc(symbol: "SPY", start: "2021-05-07", end: "2021-05-03") { time } }"#)
+            .await;
+        assert_eq!(1, response.errors.len());
+    }
+
+    #[tokio::test]
+    async fn test_gamma_exposure_includes_spot_price() {
+        let mut db = FileDb::new("data/test_spot_db.gz");
+        let mut option = OptionInfo::test();
+        option.strike = 100.0;
+        db.add_option_info("TST", vec![option]).unwrap();
+        let schema = mock_schema_with_db(Arc::new(Mutex::new(db)));
+
+        let response = schema
+            .execute(r#"{ gammaExposure(symbol: "TST") { spotPrice } }"#)
+            .await;
+
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        assert_eq!(101.5, data["gammaExposure"]["spotPrice"]);
+    }
+
+    #[tokio::test]
+    async fn test_gamma_exposure_timestamps() {
+        let mut db = FileDb::new("data/test_gex_timestamps_db.gz");
+        let fetched_at = Utc::now() - chrono::Duration::minutes(5);
+        db.add_option_info_at("TST", vec![OptionInfo::test()], fetched_at)
+            .unwrap();
+        let schema = mock_schema_with_db(Arc::new(Mutex::new(db)));
+
+        let response = schema
+            .execute(r#"{ gammaExposure(symbol: "TST") { computedAt chainTimestamp } }"#)
+            .await;
+
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        assert_eq!(
+            fetched_at.to_rfc3339(),
+            data["gammaExposure"]["chainTimestamp"]
+        );
+        let computed_at = data["gammaExposure"]["computedAt"].as_str().unwrap();
+        let computed_at = DateTime::parse_from_rfc3339(computed_at).unwrap();
+        assert!(computed_at > fetched_at);
+        assert!(computed_at <= Utc::now());
+    }
+
+    #[tokio::test]
+    async fn test_option_stats_min_open_interest() {
+        let mut db = FileDb::new("data/test_option_stats_min_oi_db.gz");
+        let option = |strike, option_type, open_interest| OptionInfo {
+            strike,
+            option_type,
+            open_interest,
+            ..OptionInfo::test()
+        };
+        db.add_option_info(
+            "TST",
+            vec![
+                option(100.0, OptionType::Call, 40),
+                option(100.0, OptionType::Put, 20),
+                option(110.0, OptionType::Put, 3),
+            ],
+        )
+        .unwrap();
+        let schema = mock_schema_with_db(Arc::new(Mutex::new(db)));
+
+        let response = schema
+            .execute(
+                r#"{ optionStats(symbol: "TST", minOpenInterest: 5) {
+                    maxPain putCallRatio strikes { strike }
+                } }"#,
+            )
+            .await;
+
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        let stats = &data["optionStats"];
+        assert_eq!(1, stats["strikes"].as_array().unwrap().len());
+        assert_eq!(100.0, stats["maxPain"]);
+        assert_eq!(0.5, stats["putCallRatio"]);
+    }
+
+    #[tokio::test]
+    async fn test_gamma_exposure_without_quote() {
+        let mut db = FileDb::new("data/test_gex_without_quote_db.gz");
+        db.add_option_info("BAD", vec![OptionInfo::test()]).unwrap();
+        let schema = mock_schema_with_db(Arc::new(Mutex::new(db)));
+
+        let response = schema
+            .execute(r#"{ gammaExposure(symbol: "BAD") { symbol spotPrice } }"#)
+            .await;
+
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        assert_eq!("BAD", data["gammaExposure"]["symbol"]);
+        assert!(data["gammaExposure"]["spotPrice"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_dollar_gamma_exposure_without_quote() {
+        let mut db = FileDb::new("data/test_dollar_gex_without_quote_db.gz");
+        db.add_option_info("BAD", vec![OptionInfo::test()]).unwrap();
+        let schema = mock_schema_with_db(Arc::new(Mutex::new(db)));
+
+        let response = schema
+            .execute(r#"{ gammaExposure(symbol: "BAD", dollar: true) { symbol } }"#)
+            .await;
+
+        assert_eq!(1, response.errors.len());
+        let response = serde_json::to_value(&response).unwrap();
+        assert_eq!("Unknown symbol: BAD", response["errors"][0]["message"]);
+        assert_eq!("BAD_SYMBOL", response["errors"][0]["extensions"]["code"]);
+    }
+
+    struct QuoteOnlyProvider;
+
+    #[async_trait]
+    impl MarketDataProvider for QuoteOnlyProvider {
+        async fn get_quote(&self, symbol: &str) -> anyhow::Result<Quote> {
+            let mut quote = MockProvider.get_quote(symbol).await?;
+            quote.last = Some(99.0);
+            Ok(quote)
+        }
+
+        async fn get_ohlc(
+            &self,
+            _symbol: &str,
+            _interval: OhlcInterval,
+            _start: Option<NaiveDate>,
+            _end: Option<NaiveDate>,
+        ) -> anyhow::Result<Vec<Ohlc>> {
+            Ok(vec![])
+        }
+
+        async fn get_option_chain(&self, _symbol: &str) -> anyhow::Result<Vec<OptionInfo>> {
+            anyhow::bail!("No option chains here")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gamma_exposure_with_quote_only_provider() {
+        let db = Arc::new(Mutex::new(FileDb::new("data/test_quote_only_db.gz")));
+        let provider = WithOptionChains::new(QuoteOnlyProvider, MockProvider);
+        let schema = schema_with_provider(db, Box::new(provider));
+
+        let response = schema
+            .execute(r#"{ gammaExposure(symbol: "SPY") { spotPrice prices { strike } } }"#)
+            .await;
+
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        assert_eq!(99.0, data["gammaExposure"]["spotPrice"]);
+        assert!(!data["gammaExposure"]["prices"]
+            .as_array()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cache_miss_fetches_from_context_provider() {
+        let db: SharedDb = Arc::new(Mutex::new(FileDb::new("data/test_cache_miss_db.gz")));
+        let schema = mock_schema_with_db(db.clone());
+
+        let response = schema
+            .execute(r#"{ optionStats(symbol: "NEW") { strikes { strike } } }"#)
+            .await;
+
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let chain = db.lock().await.option_chain("NEW").unwrap();
+        assert_eq!("NEW", chain[0].symbol);
+    }
+}
@@ -0,0 +1,239 @@
+use std::{convert::Infallible, fmt, future::Future, io::Write, sync::Arc};
+
+use async_graphql::{
+    extensions::{Extension, ExtensionContext, ExtensionFactory, NextResolve, ResolveInfo},
+    ServerResult, Value,
+};
+use log::kv::{self, VisitSource};
+use uuid::Uuid;
+use warp::Filter;
+
+const LOG_FORMAT_ENV: &str = "LOG_FORMAT";
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static CURRENT_REQUEST_ID: RequestId;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogFormat {
+    Pretty,
+    Json,
+}
+
+impl LogFormat {
+    // Pretty unless LOG_FORMAT is `json`.
+    pub fn from_env() -> Self {
+        match std::env::var(LOG_FORMAT_ENV) {
+            Ok(format) if format.eq_ignore_ascii_case("json") => LogFormat::Json,
+            _ => LogFormat::Pretty,
+        }
+    }
+}
+
+/// Installs the global logger, filtered by `RUST_LOG`.
+pub fn init(format: LogFormat) -> anyhow::Result<()> {
+    let logger = builder(format).build();
+    log::set_max_level(logger.filter());
+    log::set_boxed_logger(Box::new(RequestLogger { logger, format }))?;
+    Ok(())
+}
+
+// Tags records logged while handling a request with its id: a field in JSON,
+// a message prefix otherwise.
+struct RequestLogger {
+    logger: env_logger::Logger,
+    format: LogFormat,
+}
+
+impl log::Log for RequestLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.logger.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        match (self.format, current_request_id()) {
+            (LogFormat::Pretty, Some(request_id)) => self.logger.log(
+                &record
+                    .to_builder()
+                    .args(format_args!("[{}] {}", request_id, record.args()))
+                    .build(),
+            ),
+            _ => self.logger.log(record),
+        }
+    }
+
+    fn flush(&self) {
+        self.logger.flush()
+    }
+}
+
+fn builder(format: LogFormat) -> env_logger::Builder {
+    let mut builder = match format {
+        LogFormat::Pretty => pretty_env_logger::formatted_builder(),
+        LogFormat::Json => {
+            let mut builder = env_logger::Builder::new();
+            builder.format(|f, record| writeln!(f, "{}", json_line(record)));
+            builder
+        }
+    };
+
+    if let Ok(filters) = std::env::var("RUST_LOG") {
+        builder.parse_filters(&filters);
+    }
+    builder
+}
+
+// One JSON object per record. Key-values on the record become top-level
+// fields, keeping numbers and booleans typed.
+fn json_line(record: &log::Record) -> serde_json::Value {
+    let mut line = serde_json::json!({
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "level": record.level().to_string(),
+        "target": record.target(),
+        "message": record.args().to_string(),
+    });
+
+    if let Some(fields) = line.as_object_mut() {
+        if let Some(request_id) = current_request_id() {
+            fields.insert("request_id".to_string(), request_id.to_string().into());
+        }
+        let _ = record.key_values().visit(&mut JsonFields(fields));
+    }
+    line
+}
+
+struct JsonFields<'a>(&'a mut serde_json::Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields<'_> {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        let value = if let Some(value) = value.to_u64() {
+            value.into()
+        } else if let Some(value) = value.to_i64() {
+            value.into()
+        } else if let Some(value) = value.to_f64() {
+            value.into()
+        } else if let Some(value) = value.to_bool() {
+            value.into()
+        } else {
+            value.to_string().into()
+        };
+        self.0.insert(key.to_string(), value);
+        Ok(())
+    }
+}
+
+/// Identifies one GraphQL request across its log lines.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RequestId(pub Uuid);
+
+impl RequestId {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for RequestId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// A fresh `RequestId` for every request.
+pub fn request_id() -> impl Filter<Extract = (RequestId,), Error = Infallible> + Clone {
+    warp::any().map(RequestId::new)
+}
+
+/// Runs `future` with `request_id` attached to everything it logs.
+pub async fn with_request_id<F: Future>(request_id: RequestId, future: F) -> F::Output {
+    CURRENT_REQUEST_ID.scope(request_id, future).await
+}
+
+/// The id of the request being handled by the current task, if any.
+pub fn current_request_id() -> Option<RequestId> {
+    CURRENT_REQUEST_ID.try_with(|request_id| *request_id).ok()
+}
+
+/// Logs each top-level GraphQL field, so resolver logs can be tied back to
+/// the field that caused them.
+pub struct FieldLogger;
+
+impl ExtensionFactory for FieldLogger {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(FieldLogger)
+    }
+}
+
+#[async_trait::async_trait]
+impl Extension for FieldLogger {
+    async fn resolve(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        info: ResolveInfo<'_>,
+        next: NextResolve<'_>,
+    ) -> ServerResult<Option<Value>> {
+        if info.path_node.parent.is_none() {
+            log::info!(
+                target: "ma::graphql",
+                field = info.name;
+                "Resolving {}",
+                info.name
+            );
+        }
+        next.run(ctx, info).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_line() {
+        let fields: [(&str, kv::Value); 2] = [
+            ("field", kv::Value::from("gammaExposure")),
+            ("strikes", kv::Value::from(7u64)),
+        ];
+        let record = log::Record::builder()
+            .level(log::Level::Info)
+            .target("ma::graphql")
+            .args(format_args!("Resolving gammaExposure"))
+            .key_values(&fields)
+            .build();
+
+        let line = json_line(&record);
+
+        assert_eq!("INFO", line["level"]);
+        assert_eq!("ma::graphql", line["target"]);
+        assert_eq!("Resolving gammaExposure", line["message"]);
+        assert_eq!(7, line["strikes"]);
+        assert_eq!("gammaExposure", line["field"]);
+        assert!(line["timestamp"].is_string());
+        assert!(line.get("request_id").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_json_line_includes_current_request_id() {
+        let request_id = RequestId::new();
+        let record = log::Record::builder()
+            .args(format_args!("Querying quote"))
+            .build();
+
+        let line = with_request_id(request_id, async { json_line(&record) }).await;
+
+        assert_eq!(request_id.to_string(), line["request_id"]);
+    }
+
+    #[test]
+    fn test_builders_build() {
+        for &format in &[LogFormat::Pretty, LogFormat::Json] {
+            builder(format).build();
+        }
+    }
+}
@@ -1,20 +1,41 @@
 pub mod analysis;
+pub mod auth;
 pub mod data_apis;
 pub mod db;
 pub mod graphql;
+pub mod logging;
 pub mod math;
+pub mod rate_limit;
 pub mod types;
 pub mod utils;
 
+use analysis::gamma_exposure::GammaExposureOptions;
 use async_graphql::http::{playground_source, GraphQLPlaygroundConfig};
-use std::{convert::Infallible, sync::Arc};
-use tokio::sync::Mutex;
+use data_apis::{MarketDataProvider, Provider};
+use db::SharedDb;
+use futures::future::{self, BoxFuture, FutureExt};
+use serde::Deserialize;
+use std::{
+    convert::Infallible,
+    future::Future,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+    time::Instant,
+};
 use warp::{
     http::{Response, StatusCode},
-    Filter, Rejection,
+    Filter, Rejection, Reply,
 };
 
-use crate::db::FileDb;
+const BIND_ADDR_ENV: &str = "BIND_ADDR";
+const PORT_ENV: &str = "PORT";
+const CORS_ORIGINS_ENV: &str = "CORS_ORIGINS";
+const PREFETCH_SYMBOLS_ENV: &str = "PREFETCH_SYMBOLS";
+const TLS_CERT_PATH_ENV: &str = "TLS_CERT_PATH";
+const TLS_KEY_PATH_ENV: &str = "TLS_KEY_PATH";
+const DEFAULT_BIND_ADDR: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+const DEFAULT_PORT: u16 = 3030;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -23,42 +44,113 @@ async fn main() -> anyhow::Result<()> {
     }
 
     dotenv::dotenv().ok();
-    pretty_env_logger::init();
+    logging::init(logging::LogFormat::from_env())?;
 
-    let frontend = warp::fs::dir("frontend/public");
-    let db_download = warp::path("db").and(warp::fs::file("data/db.gz"));
+    let address = socket_address(
+        std::env::var(BIND_ADDR_ENV).ok().as_deref(),
+        std::env::var(PORT_ENV).ok().as_deref(),
+    )?;
+    let tls = tls_config(
+        std::env::var(TLS_CERT_PATH_ENV).ok(),
+        std::env::var(TLS_KEY_PATH_ENV).ok(),
+    )?;
 
-    let db = match FileDb::load() {
-        Ok(db) => db,
-        Err(_) => FileDb::default(),
-    };
-    let db = Arc::new(Mutex::new(db));
+    let db = db::load()?;
 
-    db::start_db_update_loop(db.clone())?;
+    db::start_db_update_loop(db.clone(), Provider::from_env().client())?;
 
-    let tradier_graphql_filter = warp::path("graphql").and(
-        async_graphql_warp::graphql(graphql::schema(db.clone())).and_then(
-            |(schema, request): (graphql::Schema, async_graphql::Request)| async move {
-                let resp = schema.execute(request).await;
-                Ok::<_, Infallible>(async_graphql_warp::Response::from(resp))
-            },
-        ),
+    if let Ok(symbols) = std::env::var(PREFETCH_SYMBOLS_ENV) {
+        let symbols: Vec<String> = symbols
+            .split(',')
+            .map(|symbol| symbol.trim().to_uppercase())
+            .filter(|symbol| !symbol.is_empty())
+            .collect();
+        let db = db.clone();
+        tokio::spawn(async move {
+            let provider = Provider::from_env().client();
+            let results = db::prefetch(&symbols, false, db, provider.as_ref()).await;
+            let failed = results
+                .iter()
+                .filter(|(_, status)| matches!(status, db::PrefetchStatus::Failed(_)))
+                .count();
+            log::info!("Prefetched {} symbols, {} failed", results.len(), failed);
+        });
+    }
+
+    // The playgrounds, health check and frontend stay open; only the
+    // GraphQL endpoints need a key.
+    let api_keys = auth::api_keys_from_env();
+    if api_keys.is_some() {
+        log::info!("API key authentication enabled");
+    }
+
+    let rate_limiter = rate_limit::RateLimiter::from_env().map(Arc::new);
+
+    let cors = cors(std::env::var(CORS_ORIGINS_ENV).ok().as_deref())?;
+
+    let routes = routes(
+        db.clone(),
+        graphql::schema(db),
+        Arc::from(Provider::from_env().client()),
+        rate_limiter,
+        api_keys,
     );
 
+    let log = warp::log("ma::api");
+
+    let routes = routes.with(log).with(cors);
+
+    let (address, server) = serve(routes, address, tls.as_ref(), shutdown_signal());
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    log::info!("Listening on {}://{}", scheme, address);
+    server.await;
+    log::info!("Server stopped");
+
+    Ok(())
+}
+
+// Every route the server answers, with rejections turned into JSON errors.
+// Each route matches its path before it charges the rate limit or checks the
+// API key, so a request only pays for the route that serves it.
+fn routes(
+    db: SharedDb,
+    schema: graphql::Schema,
+    provider: Arc<dyn MarketDataProvider>,
+    rate_limiter: Option<Arc<rate_limit::RateLimiter>>,
+    api_keys: Option<auth::ApiKeys>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Infallible> + Clone {
+    let health = health_route(Instant::now());
+    let frontend = warp::fs::dir("frontend/public");
+    let db_download =
+        warp::path("db").and(warp::fs::file(db::data_dir().join(db::file::FILE_NAME)));
+
+    // Each websocket upgrade takes a token, so a client can't open streams
+    // faster than it could send queries. Plain POSTs fall through to the
+    // query route without paying here.
+    let tradier_graphql_subscription = warp::path("graphql")
+        .and(warp::header::exact_ignore_case("upgrade", "websocket"))
+        .and(rate_limit::rate_limit(rate_limiter.clone()))
+        .and(auth::api_key(api_keys.clone()))
+        .and(async_graphql_warp::graphql_subscription(schema.clone()));
+
+    let gamma = gamma_route(db.clone(), provider, rate_limiter.clone(), api_keys.clone());
+
+    let tradier_graphql_filter = warp::path("graphql")
+        .and(rate_limit::rate_limit(rate_limiter))
+        .and(auth::api_key(api_keys.clone()))
+        .and(graphql_route(schema));
+
     let tradier_graphql_playground = warp::path("playground").and(warp::get()).map(|| {
         Response::builder()
             .header("content-type", "text/html")
-            .body(playground_source(GraphQLPlaygroundConfig::new("/graphql")))
+            .body(playground_source(
+                GraphQLPlaygroundConfig::new("/graphql").subscription_endpoint("/graphql"),
+            ))
     });
 
-    let tda_graphql_filter = warp::path("tdagraphql").and(
-        async_graphql_warp::graphql(graphql::tda_schema(db.clone())).and_then(
-            |(schema, request): (graphql::TdaSchema, async_graphql::Request)| async move {
-                let resp = schema.execute(request).await;
-                Ok::<_, Infallible>(async_graphql_warp::Response::from(resp))
-            },
-        ),
-    );
+    let tda_graphql_filter = warp::path("tdagraphql")
+        .and(auth::api_key(api_keys))
+        .and(graphql_route(graphql::tda_schema(db)));
 
     let tda_graphql_playground = warp::path("tdaplayground").and(warp::get()).map(|| {
         Response::builder()
@@ -68,30 +160,716 @@ async fn main() -> anyhow::Result<()> {
             )))
     });
 
-    let cors = warp::cors()
-        .allow_any_origin()
-        .allow_methods(vec!["GET", "POST", "PUT", "OPTIONS"])
-        .allow_header("content-type");
-
-    let routes = db_download
+    health
+        .or(db_download)
+        .or(gamma)
+        .or(tradier_graphql_subscription)
         .or(tradier_graphql_filter)
         .or(tradier_graphql_playground)
         .or(tda_graphql_filter)
         .or(tda_graphql_playground)
-        .or(frontend);
+        .or(frontend)
+        .recover(handle_rejection)
+}
 
-    let log = warp::log("ma::api");
+// Liveness only: answers without touching the db or any upstream API.
+fn health_route(
+    started: Instant,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path("health")
+        .and(warp::path::end())
+        .and(warp::get())
+        .map(move || {
+            warp::reply::json(&serde_json::json!({
+                "status": "ok",
+                "uptime_secs": started.elapsed().as_secs(),
+                "version": env!("CARGO_PKG_VERSION"),
+            }))
+        })
+}
 
-    warp::serve(routes.recover(handle_rejection).with(log).with(cors))
-        .run(([127, 0, 0, 1], 3030))
-        .await;
+// Executes GraphQL requests under a fresh request id. The id is in the
+// context, on every log line the request produces and in the response's
+// `x-request-id` header.
+fn graphql_route<Query, Mutation, Subscription>(
+    schema: async_graphql::Schema<Query, Mutation, Subscription>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone
+where
+    Query: async_graphql::ObjectType + 'static,
+    Mutation: async_graphql::ObjectType + 'static,
+    Subscription: async_graphql::SubscriptionType + 'static,
+{
+    logging::request_id()
+        .and(async_graphql_warp::graphql(schema))
+        .and_then(
+            |request_id: logging::RequestId,
+             (schema, request): (
+                async_graphql::Schema<Query, Mutation, Subscription>,
+                async_graphql::Request,
+            )| async move {
+                let resp =
+                    logging::with_request_id(request_id, schema.execute(request.data(request_id)))
+                        .await;
+                Ok::<_, Infallible>(warp::reply::with_header(
+                    async_graphql_warp::Response::from(resp),
+                    logging::REQUEST_ID_HEADER,
+                    request_id.to_string(),
+                ))
+            },
+        )
+}
 
-    Ok(())
+#[derive(Debug, Deserialize)]
+struct GammaQuery {
+    #[serde(default)]
+    refresh: bool,
+    expiration: Option<String>,
+}
+
+// REST mirror of the `gammaExposure` query for clients that don't speak
+// GraphQL. `refresh=true` refetches the chain before computing, and a `.csv`
+// suffix on the symbol returns the strikes as CSV instead of JSON.
+fn gamma_route(
+    db: SharedDb,
+    provider: Arc<dyn MarketDataProvider>,
+    rate_limiter: Option<Arc<rate_limit::RateLimiter>>,
+    api_keys: Option<auth::ApiKeys>,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = Rejection> + Clone {
+    warp::path!("gamma" / String)
+        .and(warp::get())
+        .and(rate_limit::rate_limit(rate_limiter))
+        .and(auth::api_key(api_keys))
+        .and(warp::query::<GammaQuery>())
+        .and_then(move |symbol: String, query: GammaQuery| {
+            let db = db.clone();
+            let provider = provider.clone();
+            async move {
+                let (symbol, csv) = match symbol.strip_suffix(".csv") {
+                    Some(symbol) => (symbol.to_string(), true),
+                    None => (symbol, false),
+                };
+
+                let result = async {
+                    if query.refresh {
+                        graphql::refresh_option_chain(&symbol, &db, provider.as_ref()).await?;
+                    }
+                    let options = GammaExposureOptions {
+                        expiration: query.expiration,
+                        ..Default::default()
+                    };
+                    graphql::gamma_exposure_stats(&symbol, &db, provider.as_ref(), options).await
+                }
+                .await;
+
+                let response = match result {
+                    Ok(stats) if csv => match utils::export::gamma_exposure_csv(&stats) {
+                        Ok(body) => warp::reply::with_header(body, "content-type", "text/csv")
+                            .into_response(),
+                        Err(e) => gamma_error_response(e),
+                    },
+                    Ok(stats) => warp::reply::json(&stats).into_response(),
+                    Err(e) => gamma_error_response(e),
+                };
+                Ok::<_, Infallible>(response)
+            }
+        })
+}
+
+fn gamma_error_response(error: anyhow::Error) -> warp::reply::Response {
+    log::error!("{}", error);
+    let code = graphql::error_code(&error);
+    let status = match code {
+        "BAD_SYMBOL" => StatusCode::NOT_FOUND,
+        "INTERNAL" => StatusCode::INTERNAL_SERVER_ERROR,
+        _ => StatusCode::BAD_GATEWAY,
+    };
+    let body = serde_json::json!({
+        "errors": [{ "message": error.to_string(), "extensions": { "code": code } }]
+    });
+    warp::reply::with_status(warp::reply::json(&body), status).into_response()
+}
+
+// Any origin is allowed unless CORS_ORIGINS lists them, comma-separated.
+fn cors(origins: Option<&str>) -> anyhow::Result<warp::cors::Builder> {
+    let cors = warp::cors()
+        .allow_methods(vec!["GET", "POST", "PUT", "OPTIONS"])
+        .allow_headers(vec!["content-type", auth::API_KEY_HEADER]);
+
+    Ok(match origins {
+        Some(origins) => {
+            let origins = origins
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(cors_origin)
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            cors.allow_origins(origins)
+        }
+        None => cors.allow_any_origin(),
+    })
+}
+
+// warp panics on origins it can't parse, so they're checked up front. An
+// origin is `scheme://host`, with an optional port and nothing after it.
+fn cors_origin(origin: &str) -> anyhow::Result<&str> {
+    let invalid = || anyhow::anyhow!("Invalid {} entry {:?}", CORS_ORIGINS_ENV, origin);
+    let origin = origin.trim_end_matches('/');
+    let uri: warp::http::Uri = origin.parse().map_err(|_| invalid())?;
+    match (uri.scheme_str(), uri.authority()) {
+        (Some(scheme), Some(authority))
+            if !authority.as_str().contains('@')
+                && origin == format!("{}://{}", scheme, authority) =>
+        {
+            Ok(origin)
+        }
+        _ => Err(invalid()),
+    }
+}
+
+fn socket_address(bind_addr: Option<&str>, port: Option<&str>) -> anyhow::Result<SocketAddr> {
+    let ip = match bind_addr {
+        Some(bind_addr) => bind_addr
+            .trim()
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid {} {:?}: {}", BIND_ADDR_ENV, bind_addr, e))?,
+        None => DEFAULT_BIND_ADDR,
+    };
+    let port = match port {
+        Some(port) => port
+            .trim()
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid {} {:?}: {}", PORT_ENV, port, e))?,
+        None => DEFAULT_PORT,
+    };
+
+    Ok(SocketAddr::new(ip, port))
+}
+
+/// PEM files for serving HTTPS. The certificate file holds the server
+/// certificate followed by any intermediates; the key file holds its private
+/// key as PKCS#8 (`BEGIN PRIVATE KEY`) or PKCS#1 (`BEGIN RSA PRIVATE KEY`).
+#[derive(Debug, PartialEq)]
+struct TlsConfig {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+}
+
+// TLS is off unless both paths are set. Setting only one is treated as a
+// mistake rather than silently serving plain HTTP.
+fn tls_config(
+    cert_path: Option<String>,
+    key_path: Option<String>,
+) -> anyhow::Result<Option<TlsConfig>> {
+    let (cert_path, key_path) = match (cert_path, key_path) {
+        (None, None) => return Ok(None),
+        (Some(cert_path), Some(key_path)) => (PathBuf::from(cert_path), PathBuf::from(key_path)),
+        _ => anyhow::bail!(
+            "{} and {} must be set together",
+            TLS_CERT_PATH_ENV,
+            TLS_KEY_PATH_ENV
+        ),
+    };
+
+    for path in [&cert_path, &key_path] {
+        if !path.is_file() {
+            anyhow::bail!("TLS file not found: {}", path.display());
+        }
+    }
+
+    Ok(Some(TlsConfig {
+        cert_path,
+        key_path,
+    }))
+}
+
+// Binds the server and returns a future that resolves once `signal` fires
+// and in-flight requests have finished.
+fn serve<F, R>(
+    routes: F,
+    address: SocketAddr,
+    tls: Option<&TlsConfig>,
+    signal: impl Future<Output = ()> + Send + 'static,
+) -> (SocketAddr, BoxFuture<'static, ()>)
+where
+    F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+    R: warp::Reply,
+{
+    match tls {
+        Some(tls) => {
+            let (address, server) = warp::serve(routes)
+                .tls()
+                .cert_path(&tls.cert_path)
+                .key_path(&tls.key_path)
+                .bind_with_graceful_shutdown(address, signal);
+            (address, server.boxed())
+        }
+        None => {
+            let (address, server) =
+                warp::serve(routes).bind_with_graceful_shutdown(address, signal);
+            (address, server.boxed())
+        }
+    }
+}
+
+// Resolves on ctrl-c, or on SIGTERM where that exists.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            log::error!("Failed to listen for ctrl-c: {}", e);
+            future::pending::<()>().await
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut stream) => {
+                stream.recv().await;
+            }
+            Err(e) => {
+                log::error!("Failed to listen for SIGTERM: {}", e);
+                future::pending::<()>().await
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    log::info!("Shutdown signal received, draining in-flight requests");
 }
 
+// Shaped like a GraphQL error response so clients can handle both the same way.
 async fn handle_rejection(err: Rejection) -> Result<impl warp::Reply, Infallible> {
-    Ok(warp::reply::with_status(
-        format!("{:?}", err),
-        StatusCode::INTERNAL_SERVER_ERROR,
-    ))
+    let (status, code, message) = if err.find::<auth::Unauthorized>().is_some() {
+        (
+            StatusCode::UNAUTHORIZED,
+            "UNAUTHORIZED",
+            "Missing or invalid API key".to_string(),
+        )
+    } else if err.find::<rate_limit::RateLimited>().is_some() {
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            "RATE_LIMITED",
+            "Too many requests".to_string(),
+        )
+    } else if err.is_not_found() {
+        (StatusCode::NOT_FOUND, "NOT_FOUND", "Not found".to_string())
+    } else if let Some(async_graphql_warp::BadRequest(e)) = err.find() {
+        (StatusCode::BAD_REQUEST, "BAD_REQUEST", e.to_string())
+    } else if err.find::<warp::reject::MethodNotAllowed>().is_some() {
+        (
+            StatusCode::METHOD_NOT_ALLOWED,
+            "METHOD_NOT_ALLOWED",
+            "Method not allowed".to_string(),
+        )
+    } else {
+        log::error!("Unhandled rejection: {:?}", err);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "INTERNAL",
+            "Internal server error".to_string(),
+        )
+    };
+
+    let body = serde_json::json!({
+        "errors": [{ "message": message, "extensions": { "code": code } }]
+    });
+
+    Ok(warp::reply::with_status(warp::reply::json(&body), status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_socket_address() {
+        assert_eq!(
+            "127.0.0.1:3030".parse::<SocketAddr>().unwrap(),
+            socket_address(None, None).unwrap()
+        );
+        assert_eq!(
+            "0.0.0.0:8080".parse::<SocketAddr>().unwrap(),
+            socket_address(Some("0.0.0.0"), Some("8080")).unwrap()
+        );
+        assert_eq!(
+            "[::]:3030".parse::<SocketAddr>().unwrap(),
+            socket_address(Some("::"), None).unwrap()
+        );
+
+        assert!(socket_address(Some("localhost"), None).is_err());
+        assert!(socket_address(None, Some("70000")).is_err());
+    }
+
+    const TEST_CERT_PATH: &str = "src/fixtures/cert.pem";
+    const TEST_KEY_PATH: &str = "src/fixtures/key.pem";
+
+    #[test]
+    fn test_tls_config() {
+        assert_eq!(None, tls_config(None, None).unwrap());
+        assert_eq!(
+            Some(TlsConfig {
+                cert_path: TEST_CERT_PATH.into(),
+                key_path: TEST_KEY_PATH.into(),
+            }),
+            tls_config(Some(TEST_CERT_PATH.into()), Some(TEST_KEY_PATH.into())).unwrap()
+        );
+
+        assert!(tls_config(Some(TEST_CERT_PATH.into()), None).is_err());
+        assert!(tls_config(Some("missing.pem".into()), Some(TEST_KEY_PATH.into())).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_serves_https_with_self_signed_cert() {
+        let tls = tls_config(Some(TEST_CERT_PATH.into()), Some(TEST_KEY_PATH.into()))
+            .unwrap()
+            .unwrap();
+        let route = warp::path("ping").map(|| "pong");
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let (address, server) = serve(
+            route,
+            ([127, 0, 0, 1], 0).into(),
+            Some(&tls),
+            rx.map(|_| ()),
+        );
+        let server = tokio::spawn(server);
+
+        let client = reqwest::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .build()
+            .unwrap();
+        let response = client
+            .get(format!("https://localhost:{}/ping", address.port()))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("pong", response.text().await.unwrap());
+
+        tx.send(()).unwrap();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_server_stops_on_shutdown_signal() {
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let route = warp::path("ping").map(|| "pong");
+        let (address, server) = serve(route, ([127, 0, 0, 1], 0).into(), None, rx.map(|_| ()));
+        let server = tokio::spawn(server);
+
+        let response = reqwest::get(format!("http://{}/ping", address))
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+
+        tx.send(()).unwrap();
+        tokio::time::timeout(std::time::Duration::from_secs(5), server)
+            .await
+            .expect("server did not shut down")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_returns_too_many_requests() {
+        let limiter = Arc::new(rate_limit::RateLimiter::new(3));
+        let route = warp::path("graphql")
+            .and(rate_limit::rate_limit(Some(limiter)))
+            .map(|| "ok")
+            .recover(handle_rejection);
+        let request = |ip: [u8; 4]| {
+            warp::test::request()
+                .path("/graphql")
+                .remote_addr((ip, 4000).into())
+        };
+
+        for _ in 0..3 {
+            let response = request([10, 0, 0, 1]).reply(&route).await;
+            assert_eq!(StatusCode::OK, response.status());
+        }
+
+        let response = request([10, 0, 0, 1]).reply(&route).await;
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, response.status());
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!("RATE_LIMITED", body["errors"][0]["extensions"]["code"]);
+
+        let response = request([10, 0, 0, 2]).reply(&route).await;
+        assert_eq!(StatusCode::OK, response.status());
+    }
+
+    #[tokio::test]
+    async fn test_api_key() {
+        let keys = Some(Arc::new(std::iter::once("secret".to_string()).collect()));
+        let route = warp::path("graphql")
+            .and(auth::api_key(keys))
+            .map(|| "ok")
+            .recover(handle_rejection);
+
+        let response = warp::test::request()
+            .path("/graphql")
+            .header(auth::API_KEY_HEADER, "secret")
+            .reply(&route)
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+
+        let response = warp::test::request()
+            .path("/graphql")
+            .header(auth::API_KEY_HEADER, "wrong")
+            .reply(&route)
+            .await;
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!("UNAUTHORIZED", body["errors"][0]["extensions"]["code"]);
+
+        let response = warp::test::request().path("/graphql").reply(&route).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+    }
+
+    #[tokio::test]
+    async fn test_api_key_disabled() {
+        let route = warp::path("graphql").and(auth::api_key(None)).map(|| "ok");
+        let response = warp::test::request().path("/graphql").reply(&route).await;
+        assert_eq!(StatusCode::OK, response.status());
+    }
+
+    struct MockProvider;
+
+    #[async_trait::async_trait]
+    impl MarketDataProvider for MockProvider {
+        async fn get_quote(&self, symbol: &str) -> anyhow::Result<types::Quote> {
+            Ok(types::Quote {
+                symbol: symbol.to_string(),
+                last: Some(101.5),
+                change: None,
+                volume: 0,
+                open: None,
+                high: None,
+                low: None,
+                close: None,
+            })
+        }
+
+        async fn get_ohlc(
+            &self,
+            _symbol: &str,
+            _interval: types::OhlcInterval,
+            _start: Option<chrono::NaiveDate>,
+            _end: Option<chrono::NaiveDate>,
+        ) -> anyhow::Result<Vec<types::Ohlc>> {
+            Ok(vec![])
+        }
+
+        async fn get_option_chain(&self, symbol: &str) -> anyhow::Result<Vec<types::OptionInfo>> {
+            let mut option = types::OptionInfo::test();
+            option.symbol = symbol.to_string();
+            option.strike = 110.0;
+            Ok(vec![option])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gamma_route() {
+        let mut db = db::FileDb::new("data/test_gamma_route_db.gz");
+        let mut option = types::OptionInfo::test();
+        option.strike = 100.0;
+        db::Database::add_option_info(&mut db, "TST", vec![option]).unwrap();
+        let db: SharedDb = Arc::new(tokio::sync::Mutex::new(db));
+        let route = gamma_route(db, Arc::new(MockProvider), None, None);
+
+        let response = warp::test::request().path("/gamma/TST").reply(&route).await;
+        assert_eq!(StatusCode::OK, response.status());
+        let stats: types::GammaExposureStats = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!("TST", stats.symbol);
+        assert_eq!(Some(101.5), stats.spot_price);
+        assert_eq!("100", stats.prices[0].strike);
+
+        let response = warp::test::request()
+            .path("/gamma/TST?refresh=true")
+            .reply(&route)
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        let stats: types::GammaExposureStats = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!("110", stats.prices[0].strike);
+
+        let response = warp::test::request()
+            .path("/gamma/TST.csv")
+            .reply(&route)
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("text/csv", response.headers()["content-type"]);
+        let body = std::str::from_utf8(response.body()).unwrap();
+        assert!(body.starts_with("strike,gamma_exposure\n110,"), "{}", body);
+    }
+
+    fn test_routes(
+        name: &str,
+        limiter: Arc<rate_limit::RateLimiter>,
+        api_keys: Option<auth::ApiKeys>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = Infallible> + Clone {
+        let mut db = db::FileDb::new(format!("data/test_{}_db.gz", name));
+        db::Database::add_option_info(&mut db, "TST", vec![types::OptionInfo::test()]).unwrap();
+        let db: SharedDb = Arc::new(tokio::sync::Mutex::new(db));
+        routes(
+            db.clone(),
+            graphql::schema_with_provider(db, Box::new(MockProvider)),
+            Arc::new(MockProvider),
+            Some(limiter),
+            api_keys,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_gamma_route_only_charges_its_own_path() {
+        let limiter = Arc::new(rate_limit::RateLimiter::new(1));
+        let keys = Some(Arc::new(std::iter::once("secret".to_string()).collect()));
+        let routes = test_routes("routes_gamma", limiter, keys);
+        let request = |path: &str| {
+            warp::test::request()
+                .path(path)
+                .remote_addr(([10, 0, 0, 1], 4000).into())
+        };
+
+        let response = request("/playground").reply(&routes).await;
+        assert_eq!(StatusCode::OK, response.status());
+        let response = request("/missing").reply(&routes).await;
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+
+        let response = request("/gamma/TST")
+            .header(auth::API_KEY_HEADER, "secret")
+            .reply(&routes)
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        let response = request("/gamma/TST")
+            .header(auth::API_KEY_HEADER, "secret")
+            .reply(&routes)
+            .await;
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, response.status());
+    }
+
+    #[tokio::test]
+    async fn test_graphql_post_takes_one_token() {
+        let limiter = Arc::new(rate_limit::RateLimiter::new(2));
+        let routes = test_routes("routes_graphql", limiter, None);
+        let request = || {
+            warp::test::request()
+                .method("POST")
+                .path("/graphql")
+                .remote_addr(([10, 0, 0, 1], 4000).into())
+                .header("content-type", "application/json")
+                .body(r#"{"query":"{ __typename }"}"#)
+        };
+
+        for _ in 0..2 {
+            let response = request().reply(&routes).await;
+            assert_eq!(StatusCode::OK, response.status());
+        }
+        let response = request().reply(&routes).await;
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, response.status());
+    }
+
+    struct RequestIdQuery;
+
+    #[async_graphql::Object]
+    impl RequestIdQuery {
+        async fn context_id(&self, context: &async_graphql::Context<'_>) -> String {
+            context.data_unchecked::<logging::RequestId>().to_string()
+        }
+
+        async fn logged_id(&self) -> Option<String> {
+            logging::current_request_id().map(|id| id.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_graphql_route_request_id() {
+        let schema = async_graphql::Schema::new(
+            RequestIdQuery,
+            async_graphql::EmptyMutation,
+            async_graphql::EmptySubscription,
+        );
+        let route = graphql_route(schema);
+
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            let response = warp::test::request()
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(r#"{"query":"{ contextId loggedId again: contextId }"}"#)
+                .reply(&route)
+                .await;
+            assert_eq!(StatusCode::OK, response.status());
+
+            let header = response.headers()[logging::REQUEST_ID_HEADER]
+                .to_str()
+                .unwrap()
+                .to_string();
+            let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+            assert_eq!(header, body["data"]["contextId"]);
+            assert_eq!(header, body["data"]["loggedId"]);
+            assert_eq!(header, body["data"]["again"]);
+            ids.push(header);
+        }
+        assert_ne!(ids[0], ids[1]);
+    }
+
+    #[tokio::test]
+    async fn test_cors_origins() {
+        let route = warp::any()
+            .map(|| "ok")
+            .with(cors(Some("https://allowed.example, https://other.example")).unwrap());
+
+        let response = warp::test::request()
+            .header("origin", "https://allowed.example")
+            .reply(&route)
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(
+            "https://allowed.example",
+            response.headers()["access-control-allow-origin"]
+        );
+
+        let response = warp::test::request()
+            .header("origin", "https://evil.example")
+            .reply(&route)
+            .await;
+        assert_eq!(StatusCode::FORBIDDEN, response.status());
+
+        let route = warp::any().map(|| "ok").with(cors(None).unwrap());
+        let response = warp::test::request()
+            .header("origin", "https://evil.example")
+            .reply(&route)
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+    }
+
+    #[test]
+    fn test_cors_rejects_malformed_origins() {
+        assert!(cors(Some("https://allowed.example:8443, http://localhost:3000")).is_ok());
+
+        assert!(cors(Some("app.example.com")).is_err());
+        assert!(cors(Some("https://allowed.example, app.example.com")).is_err());
+        assert!(cors(Some("https://")).is_err());
+        assert!(cors(Some("https://app.example.com/path")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_health_route() {
+        let route = health_route(Instant::now());
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/health")
+            .reply(&route)
+            .await;
+
+        assert_eq!(StatusCode::OK, response.status());
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!("ok", body["status"]);
+        assert!(body["uptime_secs"].is_u64());
+    }
 }
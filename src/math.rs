@@ -1,3 +1,4 @@
+pub mod binomial;
 pub mod bs;
 
 use statrs::distribution::{ContinuousCDF, Normal};
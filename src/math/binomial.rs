@@ -0,0 +1,200 @@
+#[allow(clippy::too_many_arguments)]
+pub fn american_price(
+    sigma: f64,
+    tau: f64,
+    current_price: f64,
+    strike: f64,
+    is_call: bool,
+    risk_free_rate: f64,
+    dividend_yield: f64,
+    steps: usize,
+) -> f64 {
+    price_tree(
+        sigma,
+        tau,
+        current_price,
+        strike,
+        is_call,
+        risk_free_rate,
+        dividend_yield,
+        steps,
+        true,
+    )
+    .0
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn european_price(
+    sigma: f64,
+    tau: f64,
+    current_price: f64,
+    strike: f64,
+    is_call: bool,
+    risk_free_rate: f64,
+    dividend_yield: f64,
+    steps: usize,
+) -> f64 {
+    price_tree(
+        sigma,
+        tau,
+        current_price,
+        strike,
+        is_call,
+        risk_free_rate,
+        dividend_yield,
+        steps,
+        false,
+    )
+    .0
+}
+
+/// Finite-difference gamma from the second step of the American tree.
+#[allow(clippy::too_many_arguments)]
+pub fn american_gamma(
+    sigma: f64,
+    tau: f64,
+    current_price: f64,
+    strike: f64,
+    is_call: bool,
+    risk_free_rate: f64,
+    dividend_yield: f64,
+    steps: usize,
+) -> f64 {
+    if tau <= 0.0 || sigma <= 0.0 {
+        return 0.0;
+    }
+
+    let steps = steps.max(2);
+    let (_, [down, middle, up]) = price_tree(
+        sigma,
+        tau,
+        current_price,
+        strike,
+        is_call,
+        risk_free_rate,
+        dividend_yield,
+        steps,
+        true,
+    );
+
+    let u = (sigma * (tau / steps as f64).sqrt()).exp();
+    let up_price = current_price * u * u;
+    let down_price = current_price / (u * u);
+
+    let up_delta = (up - middle) / (up_price - current_price);
+    let down_delta = (middle - down) / (current_price - down_price);
+    (up_delta - down_delta) / (0.5 * (up_price - down_price))
+}
+
+// Cox-Ross-Rubinstein tree. Returns the option value and the node values two
+// steps in, ordered from the lowest price to the highest.
+#[allow(clippy::too_many_arguments)]
+fn price_tree(
+    sigma: f64,
+    tau: f64,
+    current_price: f64,
+    strike: f64,
+    is_call: bool,
+    risk_free_rate: f64,
+    dividend_yield: f64,
+    steps: usize,
+    american: bool,
+) -> (f64, [f64; 3]) {
+    let payoff = |price: f64| {
+        if is_call {
+            (price - strike).max(0.0)
+        } else {
+            (strike - price).max(0.0)
+        }
+    };
+
+    if tau <= 0.0 || sigma <= 0.0 {
+        let value = payoff(current_price);
+        return (value, [value; 3]);
+    }
+
+    let steps = steps.max(2);
+    let dt = tau / steps as f64;
+    let u = (sigma * dt.sqrt()).exp();
+    let d = 1.0 / u;
+    let p = (((risk_free_rate - dividend_yield) * dt).exp() - d) / (u - d);
+    let discount = (-risk_free_rate * dt).exp();
+
+    let node_price =
+        |step: usize, ups: usize| current_price * u.powi(ups as i32) * d.powi((step - ups) as i32);
+
+    let mut values: Vec<f64> = (0..=steps).map(|j| payoff(node_price(steps, j))).collect();
+    let mut second_step = [0.0; 3];
+
+    for step in (0..steps).rev() {
+        for j in 0..=step {
+            let continuation = discount * (p * values[j + 1] + (1.0 - p) * values[j]);
+            values[j] = if american {
+                continuation.max(payoff(node_price(step, j)))
+            } else {
+                continuation
+            };
+        }
+
+        if step == 2 {
+            second_step = [values[0], values[1], values[2]];
+        }
+    }
+
+    (values[0], second_step)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::bs;
+
+    const EXPIRATION: f64 = 180.0 / 365.0;
+
+    #[test]
+    fn test_european_limit_matches_black_scholes() {
+        for &is_call in &[true, false] {
+            let bs_price = if is_call {
+                bs::call_price(0.3, EXPIRATION, 100.0, 105.0, 0.05, 0.02)
+            } else {
+                bs::put_price(0.3, EXPIRATION, 100.0, 105.0, 0.05, 0.02)
+            };
+
+            let coarse = european_price(0.3, EXPIRATION, 100.0, 105.0, is_call, 0.05, 0.02, 10);
+            let fine = european_price(0.3, EXPIRATION, 100.0, 105.0, is_call, 0.05, 0.02, 1000);
+
+            assert!((fine - bs_price).abs() < (coarse - bs_price).abs());
+            assert!((fine - bs_price).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_american_put_early_exercise_premium() {
+        let american = american_price(0.3, EXPIRATION, 100.0, 120.0, false, 0.05, 0.0, 500);
+        let european = european_price(0.3, EXPIRATION, 100.0, 120.0, false, 0.05, 0.0, 500);
+        assert!(american > european);
+    }
+
+    #[test]
+    fn test_american_call_without_dividend_matches_european() {
+        let american = american_price(0.3, EXPIRATION, 100.0, 105.0, true, 0.05, 0.0, 500);
+        let european = european_price(0.3, EXPIRATION, 100.0, 105.0, true, 0.05, 0.0, 500);
+        assert!((american - european).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_american_gamma_near_black_scholes() {
+        let bs_gamma = bs::gamma(0.3, EXPIRATION, 100.0, 105.0, 0.05, 0.0);
+        let tree_gamma = american_gamma(0.3, EXPIRATION, 100.0, 105.0, true, 0.05, 0.0, 500);
+        assert!(
+            (bs_gamma - tree_gamma).abs() < 1e-3,
+            "{} != {}",
+            bs_gamma,
+            tree_gamma
+        );
+        assert_eq!(
+            0.0,
+            american_gamma(0.3, 0.0, 100.0, 105.0, true, 0.05, 0.0, 500)
+        );
+    }
+}
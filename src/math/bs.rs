@@ -1,152 +1,607 @@
 use crate::math::{standard_normal_cdf, standard_normal_probability_density};
 
-const R: f64 = 0.0;
+pub const DEFAULT_RISK_FREE_RATE: f64 = 0.0;
+pub const DEFAULT_DIVIDEND_YIELD: f64 = 0.0;
+
+// One hour, in years
+const CHARM_MIN_TAU: f64 = 1.0 / (365.0 * 24.0);
+
+const IV_INITIAL_GUESS: f64 = 0.2;
+const IV_MAX_ITERATIONS: usize = 100;
+const IV_TOLERANCE: f64 = 1e-8;
+const IV_MIN_VEGA: f64 = 1e-8;
+const IV_BISECTION_LOW: f64 = 1e-4;
+const IV_BISECTION_HIGH: f64 = 10.0;
+const IV_BISECTION_MAX_ITERATIONS: usize = 200;
 
 pub fn call_price(
     sigma: f64,
-    expiration_time: f64,
-    current_time: f64,
+    tau: f64,
     current_price: f64,
     strike: f64,
+    risk_free_rate: f64,
+    dividend_yield: f64,
 ) -> f64 {
     use std::f64::consts::E;
 
-    let d1 = d1(sigma, expiration_time, current_time, current_price, strike);
-    let d2 = d2(d1, sigma, expiration_time, current_time);
-    let t = expiration_time - current_time;
+    let d1 = d1(
+        sigma,
+        tau,
+        current_price,
+        strike,
+        risk_free_rate,
+        dividend_yield,
+    );
+    let d2 = d2(d1, sigma, tau);
 
-    (standard_normal_cdf(d1) * current_price) - (standard_normal_cdf(d2) * strike * E.powf(-R * t))
+    (standard_normal_cdf(d1) * current_price * E.powf(-dividend_yield * tau))
+        - (standard_normal_cdf(d2) * strike * E.powf(-risk_free_rate * tau))
 }
 
 pub fn put_price(
     sigma: f64,
-    expiration_time: f64,
-    current_time: f64,
+    tau: f64,
     current_price: f64,
     strike: f64,
+    risk_free_rate: f64,
+    dividend_yield: f64,
 ) -> f64 {
     use std::f64::consts::E;
 
-    let d1 = d1(sigma, expiration_time, current_time, current_price, strike);
-    let d2 = d2(d1, sigma, expiration_time, current_time);
-    let t = expiration_time - current_time;
+    let d1 = d1(
+        sigma,
+        tau,
+        current_price,
+        strike,
+        risk_free_rate,
+        dividend_yield,
+    );
+    let d2 = d2(d1, sigma, tau);
 
-    (standard_normal_cdf(-d2) * strike * E.powf(-R * t))
-        - (standard_normal_cdf(-d1) * current_price)
+    (standard_normal_cdf(-d2) * strike * E.powf(-risk_free_rate * tau))
+        - (standard_normal_cdf(-d1) * current_price * E.powf(-dividend_yield * tau))
 }
 
 pub fn call_delta(
     sigma: f64,
-    expiration_time: f64,
-    current_time: f64,
+    tau: f64,
     current_price: f64,
     strike: f64,
+    risk_free_rate: f64,
+    dividend_yield: f64,
 ) -> f64 {
-    standard_normal_cdf(d1(
+    use std::f64::consts::E;
+
+    let d1 = d1(
         sigma,
-        expiration_time,
-        current_time,
+        tau,
         current_price,
         strike,
-    ))
+        risk_free_rate,
+        dividend_yield,
+    );
+    E.powf(-dividend_yield * tau) * standard_normal_cdf(d1)
 }
 
 pub fn put_delta(
     sigma: f64,
-    expiration_time: f64,
-    current_time: f64,
+    tau: f64,
     current_price: f64,
     strike: f64,
+    risk_free_rate: f64,
+    dividend_yield: f64,
 ) -> f64 {
-    standard_normal_cdf(d1(
+    use std::f64::consts::E;
+
+    let d1 = d1(
         sigma,
-        expiration_time,
-        current_time,
+        tau,
         current_price,
         strike,
-    )) - 1.0
+        risk_free_rate,
+        dividend_yield,
+    );
+    E.powf(-dividend_yield * tau) * (standard_normal_cdf(d1) - 1.0)
 }
 
+/// Returns 0.0 for expired options or a non-positive sigma rather than NaN.
 pub fn gamma(
     sigma: f64,
-    expiration_time: f64,
-    current_time: f64,
+    tau: f64,
     current_price: f64,
     strike: f64,
+    risk_free_rate: f64,
+    dividend_yield: f64,
 ) -> f64 {
-    let d1 = d1(sigma, expiration_time, current_time, current_price, strike);
-    let t = expiration_time - current_time;
-    standard_normal_probability_density(d1) / (current_price * sigma * t.sqrt())
+    use std::f64::consts::E;
+
+    if tau <= 0.0 || sigma <= 0.0 {
+        return 0.0;
+    }
+
+    let d1 = d1(
+        sigma,
+        tau,
+        current_price,
+        strike,
+        risk_free_rate,
+        dividend_yield,
+    );
+    E.powf(-dividend_yield * tau) * standard_normal_probability_density(d1)
+        / (current_price * sigma * tau.sqrt())
+}
+
+/// Evaluates gamma for a single option across a grid of underlying prices,
+/// computing the per-option terms once.
+pub fn gamma_curve(
+    sigma: f64,
+    tau: f64,
+    prices: &[f64],
+    strike: f64,
+    risk_free_rate: f64,
+    dividend_yield: f64,
+) -> Vec<f64> {
+    use std::f64::consts::E;
+
+    if tau <= 0.0 || sigma <= 0.0 {
+        return vec![0.0; prices.len()];
+    }
+
+    let sigma_sqrt_tau = sigma * tau.sqrt();
+    let ln_strike = strike.ln();
+    let drift = (risk_free_rate - dividend_yield + (sigma.powi(2) / 2.0)) * tau;
+    let dividend_discount = E.powf(-dividend_yield * tau);
+
+    prices
+        .iter()
+        .map(|price| {
+            let d1 = (price.ln() - ln_strike + drift) / sigma_sqrt_tau;
+            dividend_discount * standard_normal_probability_density(d1) / (price * sigma_sqrt_tau)
+        })
+        .collect()
 }
 
+/// Per-year theta. Divide by 365 for daily decay.
 pub fn theta(
     sigma: f64,
-    expiration_time: f64,
-    current_time: f64,
+    tau: f64,
     current_price: f64,
     strike: f64,
+    is_call: bool,
+    risk_free_rate: f64,
+    dividend_yield: f64,
 ) -> f64 {
     use std::f64::consts::E;
 
-    let d1 = d1(sigma, expiration_time, current_time, current_price, strike);
-    let d2 = d2(d1, sigma, expiration_time, current_time);
-    let t = expiration_time - current_time;
+    let d1 = d1(
+        sigma,
+        tau,
+        current_price,
+        strike,
+        risk_free_rate,
+        dividend_yield,
+    );
+    let d2 = d2(d1, sigma, tau);
+    let dividend_discount = E.powf(-dividend_yield * tau);
+
+    let a = (current_price * dividend_discount * standard_normal_probability_density(d1) * sigma)
+        / (2.0 * tau.sqrt());
+
+    if is_call {
+        let b = risk_free_rate * strike * E.powf(-risk_free_rate * tau) * standard_normal_cdf(d2);
+        let c = dividend_yield * current_price * dividend_discount * standard_normal_cdf(d1);
+        -a - b + c
+    } else {
+        let b = risk_free_rate * strike * E.powf(-risk_free_rate * tau) * standard_normal_cdf(-d2);
+        let c = dividend_yield * current_price * dividend_discount * standard_normal_cdf(-d1);
+        -a + b - c
+    }
+}
 
-    let a = (current_price * standard_normal_probability_density(d1) * sigma) / (2.0 * t.sqrt());
-    let b = R * strike * E.powf(-R * t) * standard_normal_cdf(d2);
+pub fn vega(
+    sigma: f64,
+    tau: f64,
+    current_price: f64,
+    strike: f64,
+    risk_free_rate: f64,
+    dividend_yield: f64,
+) -> f64 {
+    use std::f64::consts::E;
 
-    (-a - b) / 365.0
+    let d1 = d1(
+        sigma,
+        tau,
+        current_price,
+        strike,
+        risk_free_rate,
+        dividend_yield,
+    );
+
+    current_price
+        * E.powf(-dividend_yield * tau)
+        * standard_normal_probability_density(d1)
+        * tau.sqrt()
 }
 
-pub fn vega(
+/// Rho per 1.00 change in the risk-free rate. Divide by 100 for per-percent.
+pub fn rho(
     sigma: f64,
-    expiration_time: f64,
-    current_time: f64,
+    tau: f64,
     current_price: f64,
     strike: f64,
+    is_call: bool,
+    risk_free_rate: f64,
+    dividend_yield: f64,
 ) -> f64 {
-    let d1 = d1(sigma, expiration_time, current_time, current_price, strike);
-    let t = expiration_time - current_time;
+    use std::f64::consts::E;
 
-    (current_price * standard_normal_probability_density(d1) * t.sqrt()) / 100.0
+    let d1 = d1(
+        sigma,
+        tau,
+        current_price,
+        strike,
+        risk_free_rate,
+        dividend_yield,
+    );
+    let d2 = d2(d1, sigma, tau);
+
+    if is_call {
+        strike * tau * E.powf(-risk_free_rate * tau) * standard_normal_cdf(d2)
+    } else {
+        -strike * tau * E.powf(-risk_free_rate * tau) * standard_normal_cdf(-d2)
+    }
 }
 
 pub fn vanna(
     sigma: f64,
-    expiration_time: f64,
-    current_time: f64,
+    tau: f64,
     current_price: f64,
     strike: f64,
+    risk_free_rate: f64,
+    dividend_yield: f64,
 ) -> f64 {
-    let d1 = d1(sigma, expiration_time, current_time, current_price, strike);
-    let d2 = d2(d1, sigma, expiration_time, current_time);
-    (-1.0 * standard_normal_probability_density(d1) * (d2 / sigma)) / 100.0
+    use std::f64::consts::E;
+
+    let d1 = d1(
+        sigma,
+        tau,
+        current_price,
+        strike,
+        risk_free_rate,
+        dividend_yield,
+    );
+    let d2 = d2(d1, sigma, tau);
+    -E.powf(-dividend_yield * tau) * standard_normal_probability_density(d1) * (d2 / sigma)
 }
 
+/// Charm blows up as 1/tau near expiration, so tau is floored at one hour.
+/// Expired options return 0.0.
 pub fn charm(
     sigma: f64,
-    expiration_time: f64,
-    current_time: f64,
+    tau: f64,
     current_price: f64,
     strike: f64,
+    is_call: bool,
+    risk_free_rate: f64,
+    dividend_yield: f64,
 ) -> f64 {
-    let t = expiration_time - current_time;
-    let d1 = d1(sigma, expiration_time, current_time, current_price, strike);
-    let d2 = d2(d1, sigma, expiration_time, current_time);
+    use std::f64::consts::E;
+
+    if tau <= 0.0 {
+        return 0.0;
+    }
+    let tau = tau.max(CHARM_MIN_TAU);
 
-    let numerator = (2.0 * R * t) - (d2 * sigma * t.sqrt());
-    let denominator = 2.0 * t * sigma * t.sqrt();
-    -standard_normal_probability_density(d1) * (numerator / denominator)
+    let d1 = d1(
+        sigma,
+        tau,
+        current_price,
+        strike,
+        risk_free_rate,
+        dividend_yield,
+    );
+    let d2 = d2(d1, sigma, tau);
+    let dividend_discount = E.powf(-dividend_yield * tau);
+
+    let numerator = (2.0 * (risk_free_rate - dividend_yield) * tau) - (d2 * sigma * tau.sqrt());
+    let denominator = 2.0 * tau * sigma * tau.sqrt();
+    let decay =
+        dividend_discount * standard_normal_probability_density(d1) * (numerator / denominator);
+
+    if is_call {
+        (dividend_yield * dividend_discount * standard_normal_cdf(d1)) - decay
+    } else {
+        -(dividend_yield * dividend_discount * standard_normal_cdf(-d1)) - decay
+    }
 }
 
-fn d1(sigma: f64, expiration_time: f64, current_time: f64, current_price: f64, strike: f64) -> f64 {
-    let t = expiration_time - current_time;
-    ((current_price / strike).ln() + (R + (sigma.powi(2) / 2.0)) * t) / (sigma * t.sqrt())
+/// Risk-neutral probability that the option finishes in the money.
+pub fn prob_itm(
+    sigma: f64,
+    tau: f64,
+    current_price: f64,
+    strike: f64,
+    is_call: bool,
+    risk_free_rate: f64,
+    dividend_yield: f64,
+) -> f64 {
+    let d1 = d1(
+        sigma,
+        tau,
+        current_price,
+        strike,
+        risk_free_rate,
+        dividend_yield,
+    );
+    let d2 = d2(d1, sigma, tau);
+
+    if is_call {
+        standard_normal_cdf(d2)
+    } else {
+        standard_normal_cdf(-d2)
+    }
 }
 
-fn d2(d1: f64, sigma: f64, expiration_time: f64, current_time: f64) -> f64 {
-    let t = expiration_time - current_time;
-    d1 - (sigma * t.sqrt())
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Greeks {
+    pub delta: f64,
+    pub gamma: f64,
+    pub theta: f64,
+    pub vega: f64,
+    pub rho: f64,
+}
+
+impl Greeks {
+    /// Computes all greeks from a single evaluation of d1/d2. Each value matches
+    /// its standalone function.
+    pub fn compute(
+        sigma: f64,
+        tau: f64,
+        current_price: f64,
+        strike: f64,
+        is_call: bool,
+        risk_free_rate: f64,
+        dividend_yield: f64,
+    ) -> Self {
+        use std::f64::consts::E;
+
+        let d1 = d1(
+            sigma,
+            tau,
+            current_price,
+            strike,
+            risk_free_rate,
+            dividend_yield,
+        );
+        let d2 = d2(d1, sigma, tau);
+        let pdf_d1 = standard_normal_probability_density(d1);
+        let dividend_discount = E.powf(-dividend_yield * tau);
+        let strike_discount = E.powf(-risk_free_rate * tau);
+
+        let gamma = if tau <= 0.0 || sigma <= 0.0 {
+            0.0
+        } else {
+            dividend_discount * pdf_d1 / (current_price * sigma * tau.sqrt())
+        };
+        let vega = current_price * dividend_discount * pdf_d1 * tau.sqrt();
+        let a = (current_price * dividend_discount * pdf_d1 * sigma) / (2.0 * tau.sqrt());
+
+        if is_call {
+            Self {
+                delta: dividend_discount * standard_normal_cdf(d1),
+                gamma,
+                theta: -a - risk_free_rate * strike * strike_discount * standard_normal_cdf(d2)
+                    + dividend_yield * current_price * dividend_discount * standard_normal_cdf(d1),
+                vega,
+                rho: strike * tau * strike_discount * standard_normal_cdf(d2),
+            }
+        } else {
+            Self {
+                delta: dividend_discount * (standard_normal_cdf(d1) - 1.0),
+                gamma,
+                theta: -a + risk_free_rate * strike * strike_discount * standard_normal_cdf(-d2)
+                    - dividend_yield * current_price * dividend_discount * standard_normal_cdf(-d1),
+                vega,
+                rho: -strike * tau * strike_discount * standard_normal_cdf(-d2),
+            }
+        }
+    }
+}
+
+/// How far observed prices deviate from put-call parity, as
+/// `(call - put) - (S * e^(-q * tau) - K * e^(-r * tau))`. A positive residual
+/// means calls are rich relative to puts, a negative one means puts are rich.
+pub fn put_call_parity_residual(
+    call_price: f64,
+    put_price: f64,
+    current_price: f64,
+    strike: f64,
+    tau: f64,
+    risk_free_rate: f64,
+    dividend_yield: f64,
+) -> f64 {
+    use std::f64::consts::E;
+
+    let forward =
+        current_price * E.powf(-dividend_yield * tau) - strike * E.powf(-risk_free_rate * tau);
+    (call_price - put_price) - forward
+}
+
+/// Solves for the volatility that reproduces `market_price` using Newton-Raphson,
+/// falling back to bisection when Newton fails to converge. Returns `None` if the
+/// price is below intrinsic value or neither solver converges.
+pub fn implied_volatility(
+    market_price: f64,
+    current_price: f64,
+    strike: f64,
+    tau: f64,
+    is_call: bool,
+    risk_free_rate: f64,
+    dividend_yield: f64,
+) -> Option<f64> {
+    use std::f64::consts::E;
+
+    let discounted_price = current_price * E.powf(-dividend_yield * tau);
+    let discounted_strike = strike * E.powf(-risk_free_rate * tau);
+    let intrinsic = if is_call {
+        (discounted_price - discounted_strike).max(0.0)
+    } else {
+        (discounted_strike - discounted_price).max(0.0)
+    };
+
+    if tau <= 0.0 || market_price < intrinsic {
+        return None;
+    }
+
+    implied_volatility_newton(
+        market_price,
+        current_price,
+        strike,
+        tau,
+        is_call,
+        risk_free_rate,
+        dividend_yield,
+    )
+    .or_else(|| {
+        implied_volatility_bisection(
+            market_price,
+            current_price,
+            strike,
+            tau,
+            is_call,
+            risk_free_rate,
+            dividend_yield,
+            IV_BISECTION_LOW,
+            IV_BISECTION_HIGH,
+        )
+    })
+}
+
+/// Brackets the implied volatility between `lo` and `hi`. Returns `None` if
+/// `market_price` isn't achievable within that range.
+#[allow(clippy::too_many_arguments)]
+pub fn implied_volatility_bisection(
+    market_price: f64,
+    current_price: f64,
+    strike: f64,
+    tau: f64,
+    is_call: bool,
+    risk_free_rate: f64,
+    dividend_yield: f64,
+    lo: f64,
+    hi: f64,
+) -> Option<f64> {
+    let price = if is_call { call_price } else { put_price };
+
+    let mut lo = lo;
+    let mut hi = hi;
+    if market_price
+        < price(
+            lo,
+            tau,
+            current_price,
+            strike,
+            risk_free_rate,
+            dividend_yield,
+        )
+        || market_price
+            > price(
+                hi,
+                tau,
+                current_price,
+                strike,
+                risk_free_rate,
+                dividend_yield,
+            )
+    {
+        return None;
+    }
+
+    for _ in 0..IV_BISECTION_MAX_ITERATIONS {
+        let mid = (lo + hi) / 2.0;
+        let diff = price(
+            mid,
+            tau,
+            current_price,
+            strike,
+            risk_free_rate,
+            dividend_yield,
+        ) - market_price;
+        if diff.abs() < IV_TOLERANCE || (hi - lo) < IV_TOLERANCE {
+            return Some(mid);
+        }
+
+        if diff > 0.0 {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+
+    None
+}
+
+fn implied_volatility_newton(
+    market_price: f64,
+    current_price: f64,
+    strike: f64,
+    tau: f64,
+    is_call: bool,
+    risk_free_rate: f64,
+    dividend_yield: f64,
+) -> Option<f64> {
+    let price = if is_call { call_price } else { put_price };
+
+    let mut sigma = IV_INITIAL_GUESS;
+    for _ in 0..IV_MAX_ITERATIONS {
+        let diff = price(
+            sigma,
+            tau,
+            current_price,
+            strike,
+            risk_free_rate,
+            dividend_yield,
+        ) - market_price;
+        if diff.abs() < IV_TOLERANCE {
+            return Some(sigma);
+        }
+
+        let vega = vega(
+            sigma,
+            tau,
+            current_price,
+            strike,
+            risk_free_rate,
+            dividend_yield,
+        );
+        if vega < IV_MIN_VEGA {
+            return None;
+        }
+
+        sigma -= diff / vega;
+        if !sigma.is_finite() || sigma <= 0.0 {
+            return None;
+        }
+    }
+
+    None
+}
+
+pub fn d1(
+    sigma: f64,
+    tau: f64,
+    current_price: f64,
+    strike: f64,
+    risk_free_rate: f64,
+    dividend_yield: f64,
+) -> f64 {
+    ((current_price / strike).ln()
+        + (risk_free_rate - dividend_yield + (sigma.powi(2) / 2.0)) * tau)
+        / (sigma * tau.sqrt())
+}
+
+pub fn d2(d1: f64, sigma: f64, tau: f64) -> f64 {
+    d1 - (sigma * tau.sqrt())
 }
 
 #[cfg(test)]
@@ -156,53 +611,282 @@ mod tests {
     const FLOAT_ERROR: f64 = 0.0001;
     const EXPIRATION: f64 = 180.0 / 365.0;
 
+    #[test]
+    fn test_d1_d2() {
+        let d1 = d1(0.5, EXPIRATION, 10.0, 9.0, 0.0, 0.0);
+        assert_float_eq(0.4756, d1);
+        assert_float_eq(0.1245, d2(d1, 0.5, EXPIRATION));
+    }
+
+    #[test]
+    fn test_risk_free_rate_moves_d2_and_gamma() {
+        let d1 = d1(0.5, EXPIRATION, 10.0, 9.0, 0.05, 0.0);
+        assert_float_eq(0.1947, d2(d1, 0.5, EXPIRATION));
+        assert_float_eq(0.0979, gamma(0.5, EXPIRATION, 10.0, 9.0, 0.05, 0.0));
+    }
+
     #[test]
     fn test_call_price() {
-        assert_float_eq(1.8824, call_price(0.5, EXPIRATION, 0.0, 10.0, 9.0));
+        assert_float_eq(1.8824, call_price(0.5, EXPIRATION, 10.0, 9.0, 0.0, 0.0));
     }
 
     #[test]
     fn test_put_price() {
-        assert_float_eq(0.8824, put_price(0.5, EXPIRATION, 0.0, 10.0, 9.0));
+        assert_float_eq(0.8824, put_price(0.5, EXPIRATION, 10.0, 9.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_put_call_parity() {
+        use std::f64::consts::E;
+
+        for &sigma in &[0.1, 0.5, 1.0] {
+            for &strike in &[5.0, 9.0, 10.0, 15.0] {
+                for &(r, q) in &[(0.0, 0.0), (0.05, 0.0), (0.05, 0.03)] {
+                    let call = call_price(sigma, EXPIRATION, 10.0, strike, r, q);
+                    let put = put_price(sigma, EXPIRATION, 10.0, strike, r, q);
+                    let forward = 10.0 * E.powf(-q * EXPIRATION) - strike * E.powf(-r * EXPIRATION);
+                    assert_float_eq(call - put, forward);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_prob_itm() {
+        assert_float_eq(0.5495, prob_itm(0.5, EXPIRATION, 10.0, 9.0, true, 0.0, 0.0));
+
+        for &strike in &[80.0, 100.0, 120.0] {
+            let call = prob_itm(0.3, EXPIRATION, 100.0, strike, true, 0.05, 0.02);
+            let put = prob_itm(0.3, EXPIRATION, 100.0, strike, false, 0.05, 0.02);
+            assert_float_eq(1.0, call + put);
+        }
+    }
+
+    #[test]
+    fn test_greeks_compute() {
+        for &is_call in &[true, false] {
+            let greeks = Greeks::compute(0.5, EXPIRATION, 10.0, 9.0, is_call, 0.05, 0.02);
+            let delta = if is_call { call_delta } else { put_delta };
+
+            assert_eq!(gamma(0.5, EXPIRATION, 10.0, 9.0, 0.05, 0.02), greeks.gamma);
+            assert_float_eq(delta(0.5, EXPIRATION, 10.0, 9.0, 0.05, 0.02), greeks.delta);
+            assert_float_eq(
+                theta(0.5, EXPIRATION, 10.0, 9.0, is_call, 0.05, 0.02),
+                greeks.theta,
+            );
+            assert_float_eq(vega(0.5, EXPIRATION, 10.0, 9.0, 0.05, 0.02), greeks.vega);
+            assert_float_eq(
+                rho(0.5, EXPIRATION, 10.0, 9.0, is_call, 0.05, 0.02),
+                greeks.rho,
+            );
+        }
+    }
+
+    #[test]
+    fn test_put_call_parity_residual() {
+        let call = call_price(0.5, EXPIRATION, 10.0, 9.0, 0.05, 0.02);
+        let put = put_price(0.5, EXPIRATION, 10.0, 9.0, 0.05, 0.02);
+        assert_float_eq(
+            0.0,
+            put_call_parity_residual(call, put, 10.0, 9.0, EXPIRATION, 0.05, 0.02),
+        );
+
+        let residual =
+            put_call_parity_residual(call + 0.25, put, 10.0, 9.0, EXPIRATION, 0.05, 0.02);
+        assert_float_eq(0.25, residual);
+
+        let residual =
+            put_call_parity_residual(call, put + 0.25, 10.0, 9.0, EXPIRATION, 0.05, 0.02);
+        assert_float_eq(-0.25, residual);
     }
 
     #[test]
     fn test_call_delta() {
-        assert_float_eq(0.6828, call_delta(0.5, EXPIRATION, 0.0, 10.0, 9.0));
+        assert_float_eq(0.6828, call_delta(0.5, EXPIRATION, 10.0, 9.0, 0.0, 0.0));
     }
 
     #[test]
     fn test_put_delta() {
-        assert_float_eq(-0.3172, put_delta(0.5, EXPIRATION, 0.0, 10.0, 9.0));
+        assert_float_eq(-0.3172, put_delta(0.5, EXPIRATION, 10.0, 9.0, 0.0, 0.0));
     }
 
     #[test]
     fn test_gamma() {
-        assert_float_eq(0.1015, gamma(0.5, EXPIRATION, 0.0, 10.0, 9.0));
+        assert_float_eq(0.1015, gamma(0.5, EXPIRATION, 10.0, 9.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_gamma_expired() {
+        assert_eq!(0.0, gamma(0.5, 0.0, 10.0, 10.0, 0.0, 0.0));
+        assert_eq!(0.0, gamma(0.5, -0.1, 10.0, 10.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_gamma_zero_sigma() {
+        assert_eq!(0.0, gamma(0.0, EXPIRATION, 10.0, 10.0, 0.0, 0.0));
+        assert_eq!(0.0, gamma(-0.5, EXPIRATION, 10.0, 10.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_gamma_curve_matches_gamma() {
+        let prices: Vec<f64> = (0..200).map(|i| 50.0 + i as f64 * 0.5).collect();
+        let curve = gamma_curve(0.3, EXPIRATION, &prices, 100.0, 0.05, 0.02);
+
+        assert_eq!(prices.len(), curve.len());
+        for (price, curve_gamma) in prices.iter().zip(curve) {
+            let gamma = gamma(0.3, EXPIRATION, *price, 100.0, 0.05, 0.02);
+            assert!(
+                (gamma - curve_gamma).abs() < 1e-12,
+                "{} != {}",
+                gamma,
+                curve_gamma
+            );
+        }
+    }
+
+    #[test]
+    fn test_gamma_with_dividend_yield() {
+        assert_float_eq(0.1021, gamma(0.5, EXPIRATION, 10.0, 9.0, 0.0, 0.05));
+
+        // A dividend lowers the forward, so OTM call strikes lose gamma
+        let otm_without_dividend = gamma(0.3, EXPIRATION, 100.0, 110.0, 0.0, 0.0);
+        let otm_with_dividend = gamma(0.3, EXPIRATION, 100.0, 110.0, 0.0, 0.05);
+        assert!(otm_with_dividend < otm_without_dividend);
     }
 
     #[test]
     fn test_theta() {
-        assert_float_eq(-0.0035, theta(0.5, EXPIRATION, 0.0, 10.0, 9.0));
+        assert_float_eq(-1.2683, theta(0.5, EXPIRATION, 10.0, 9.0, true, 0.0, 0.0));
+        assert_float_eq(-1.2683, theta(0.5, EXPIRATION, 10.0, 9.0, false, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_theta_with_rates() {
+        // Reference values are -dV/dtau of the Black-Scholes price, taken by
+        // central difference.
+        assert_float_eq(-5.0893, theta(0.2, 1.0, 100.0, 100.0, true, 0.05, 0.02));
+        assert_float_eq(-2.2936, theta(0.2, 1.0, 100.0, 100.0, false, 0.05, 0.02));
+    }
+
+    #[test]
+    fn test_theta_atm_is_negative() {
+        assert!(theta(0.3, EXPIRATION, 100.0, 100.0, true, 0.0, 0.0) < 0.0);
+        assert!(theta(0.3, EXPIRATION, 100.0, 100.0, false, 0.0, 0.0) < 0.0);
     }
 
     #[test]
     fn test_vega() {
-        assert_float_eq(0.0250, vega(0.5, EXPIRATION, 0.0, 10.0, 9.0));
+        assert_float_eq(2.5019, vega(0.5, EXPIRATION, 10.0, 9.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_vega_peaks_near_the_money() {
+        let atm = vega(0.3, EXPIRATION, 100.0, 100.0, 0.0, 0.0);
+        let itm = vega(0.3, EXPIRATION, 100.0, 80.0, 0.0, 0.0);
+        let otm = vega(0.3, EXPIRATION, 100.0, 120.0, 0.0, 0.0);
+
+        assert!(atm > itm);
+        assert!(atm > otm);
+        assert!(vega(0.3, EXPIRATION, 100.0, 10.0, 0.0, 0.0) < FLOAT_ERROR);
+        assert!(vega(0.3, EXPIRATION, 100.0, 1000.0, 0.0, 0.0) < FLOAT_ERROR);
+    }
+
+    #[test]
+    fn test_call_rho() {
+        let rho = rho(0.5, EXPIRATION, 10.0, 9.0, true, 0.0, 0.0);
+        assert!(rho > 0.0);
+        assert_float_eq(2.4391, rho);
+    }
+
+    #[test]
+    fn test_put_rho() {
+        let rho = rho(0.5, EXPIRATION, 10.0, 9.0, false, 0.0, 0.0);
+        assert!(rho < 0.0);
+        assert_float_eq(-1.9993, rho);
     }
 
     #[test]
     fn test_vanna() {
-        assert_float_eq(-0.01798, vanna(0.1, EXPIRATION, 0.0, 10.0, 9.0));
-        assert_float_eq(-0.0008871, vanna(0.5, EXPIRATION, 0.0, 10.0, 9.0));
-        assert_float_eq(0.0007075, vanna(1.0, EXPIRATION, 0.0, 10.0, 9.0));
+        assert_float_eq(-1.7983, vanna(0.1, EXPIRATION, 10.0, 9.0, 0.0, 0.0));
+        assert_float_eq(-0.0887, vanna(0.5, EXPIRATION, 10.0, 9.0, 0.0, 0.0));
+        assert_float_eq(0.0708, vanna(1.0, EXPIRATION, 10.0, 9.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_vanna_sign_across_strike() {
+        assert!(vanna(0.3, EXPIRATION, 80.0, 100.0, 0.0, 0.0) > 0.0);
+        assert!(vanna(0.3, EXPIRATION, 120.0, 100.0, 0.0, 0.0) < 0.0);
+
+        // Vanna crosses zero where d2 == 0, just above the strike
+        let crossing = 100.0 * (0.3_f64.powi(2) * EXPIRATION / 2.0).exp();
+        assert_float_eq(0.0, vanna(0.3, EXPIRATION, crossing, 100.0, 0.0, 0.0));
     }
 
     #[test]
     fn test_charm() {
-        assert_float_eq(0.1823, charm(0.1, EXPIRATION, 0.0, 10.0, 9.0));
-        assert_float_eq(0.0449, charm(0.5, EXPIRATION, 0.0, 10.0, 9.0));
-        assert_float_eq(-0.0717, charm(1.0, EXPIRATION, 0.0, 10.0, 9.0));
+        assert_float_eq(0.1823, charm(0.1, EXPIRATION, 10.0, 9.0, true, 0.0, 0.0));
+        assert_float_eq(0.0449, charm(0.5, EXPIRATION, 10.0, 9.0, true, 0.0, 0.0));
+        assert_float_eq(-0.0717, charm(1.0, EXPIRATION, 10.0, 9.0, true, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_put_charm() {
+        use std::f64::consts::E;
+
+        assert_float_eq(0.0449, charm(0.5, EXPIRATION, 10.0, 9.0, false, 0.0, 0.0));
+
+        let q = 0.03;
+        let call = charm(0.5, EXPIRATION, 10.0, 9.0, true, 0.05, q);
+        let put = charm(0.5, EXPIRATION, 10.0, 9.0, false, 0.05, q);
+        assert_float_eq(call - put, q * E.powf(-q * EXPIRATION));
+    }
+
+    #[test]
+    fn test_charm_near_expiration() {
+        assert_float_eq(0.0, charm(0.5, 0.0, 10.0, 9.0, true, 0.0, 0.0));
+
+        let floored = charm(0.5, CHARM_MIN_TAU, 10.0, 10.0, true, 0.0, 0.0);
+        let tiny = charm(0.5, 1e-12, 10.0, 10.0, true, 0.0, 0.0);
+        assert!(tiny.is_finite());
+        assert_float_eq(floored, tiny);
+    }
+
+    #[test]
+    fn test_implied_volatility_round_trip() {
+        let call = call_price(0.35, EXPIRATION, 100.0, 105.0, 0.0, 0.0);
+        let iv = implied_volatility(call, 100.0, 105.0, EXPIRATION, true, 0.0, 0.0).unwrap();
+        assert_float_eq(call, call_price(iv, EXPIRATION, 100.0, 105.0, 0.0, 0.0));
+
+        let put = put_price(0.35, EXPIRATION, 100.0, 105.0, 0.0, 0.0);
+        let iv = implied_volatility(put, 100.0, 105.0, EXPIRATION, false, 0.0, 0.0).unwrap();
+        assert_float_eq(put, put_price(iv, EXPIRATION, 100.0, 105.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_implied_volatility_below_intrinsic() {
+        assert!(implied_volatility(4.0, 100.0, 95.0, EXPIRATION, true, 0.0, 0.0).is_none());
+        assert!(implied_volatility(4.0, 95.0, 100.0, EXPIRATION, false, 0.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn test_implied_volatility_deep_otm_falls_back_to_bisection() {
+        let tau = 0.1;
+        let call = call_price(1.0, tau, 100.0, 200.0, 0.0, 0.0);
+
+        assert!(implied_volatility_newton(call, 100.0, 200.0, tau, true, 0.0, 0.0).is_none());
+        assert_float_eq(
+            1.0,
+            implied_volatility(call, 100.0, 200.0, tau, true, 0.0, 0.0).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_implied_volatility_bisection_out_of_range() {
+        let call = call_price(1.0, EXPIRATION, 100.0, 105.0, 0.0, 0.0);
+        assert!(implied_volatility_bisection(
+            call, 100.0, 105.0, EXPIRATION, true, 0.0, 0.0, 0.1, 0.5
+        )
+        .is_none());
     }
 
     fn assert_float_eq(actual: f64, expected: f64) {
@@ -0,0 +1,130 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use warp::{Filter, Rejection};
+
+const RATE_LIMIT_ENV: &str = "RATE_LIMIT_PER_MINUTE";
+const DEFAULT_PER_MINUTE: u32 = 120;
+// Past this many tracked clients, buckets that have refilled are dropped.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+#[derive(Debug)]
+pub struct RateLimited;
+
+impl warp::reject::Reject for RateLimited {}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// Token bucket per client IP. Each client can burst up to the per-minute
+/// limit, and tokens refill evenly over the minute.
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_sec: f64,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: u32) -> Self {
+        Self {
+            capacity: per_minute as f64,
+            refill_per_sec: per_minute as f64 / 60.0,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Unset uses the default; 0 turns rate limiting off.
+    pub fn from_env() -> Option<Self> {
+        let per_minute = std::env::var(RATE_LIMIT_ENV)
+            .ok()
+            .and_then(|limit| limit.parse().ok())
+            .unwrap_or(DEFAULT_PER_MINUTE);
+        if per_minute == 0 {
+            None
+        } else {
+            Some(Self::new(per_minute))
+        }
+    }
+
+    pub fn check(&self, ip: IpAddr) -> bool {
+        self.check_at(ip, Instant::now())
+    }
+
+    fn check_at(&self, ip: IpAddr, now: Instant) -> bool {
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            let (capacity, refill_per_sec) = (self.capacity, self.refill_per_sec);
+            buckets.retain(|_, bucket| {
+                let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+                bucket.tokens + elapsed * refill_per_sec < capacity
+            });
+        }
+
+        let bucket = buckets.entry(ip).or_insert(Bucket {
+            tokens: self.capacity,
+            updated_at: now,
+        });
+
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Rejects with `RateLimited` once the client has used up its bucket.
+/// Requests without a remote address are let through.
+pub fn rate_limit(
+    limiter: Option<Arc<RateLimiter>>,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::addr::remote()
+        .and_then(move |address: Option<SocketAddr>| {
+            let limiter = limiter.clone();
+            async move {
+                match (limiter, address) {
+                    (Some(limiter), Some(address)) if !limiter.check(address.ip()) => {
+                        Err(warp::reject::custom(RateLimited))
+                    }
+                    _ => Ok(()),
+                }
+            }
+        })
+        .untuple_one()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_bucket_refills_over_time() {
+        let limiter = RateLimiter::new(60);
+        let client: IpAddr = [10, 0, 0, 1].into();
+        let other: IpAddr = [10, 0, 0, 2].into();
+        let start = Instant::now();
+
+        for _ in 0..60 {
+            assert!(limiter.check_at(client, start));
+        }
+        assert!(!limiter.check_at(client, start));
+        assert!(limiter.check_at(other, start));
+
+        assert!(!limiter.check_at(client, start + Duration::from_millis(500)));
+        assert!(limiter.check_at(client, start + Duration::from_secs(1)));
+        assert!(!limiter.check_at(client, start + Duration::from_secs(1)));
+    }
+}
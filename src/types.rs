@@ -1,12 +1,18 @@
 pub mod clock;
+pub mod dashboard;
 pub mod gex;
+pub mod indicators;
 pub mod ohlc;
 pub mod options;
 pub mod quote;
 pub mod stats;
+pub mod symbol;
 
 pub use clock::Clock;
-pub use gex::{GammaExposure, GammaExposureStats};
+pub use dashboard::Dashboard;
+pub use gex::{GammaExposure, GammaExposureStats, GammaProfilePoint};
+pub use indicators::{BollingerPoint, IndicatorPoint};
 pub use ohlc::{Ohlc, OhlcInterval};
 pub use options::{Greeks, OptionInfo, OptionType};
-pub use quote::Quote;
+pub use quote::{Quote, QuoteResult};
+pub use symbol::SymbolInfo;
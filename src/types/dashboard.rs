@@ -0,0 +1,11 @@
+use async_graphql::SimpleObject;
+use serde::{Deserialize, Serialize};
+
+use super::{stats::OptionStats, GammaExposureStats, Quote};
+
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub struct Dashboard {
+    pub quote: Quote,
+    pub option_stats: OptionStats,
+    pub gamma_exposure: GammaExposureStats,
+}
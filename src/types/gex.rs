@@ -5,13 +5,27 @@ use serde::{Deserialize, Serialize};
 pub struct GammaExposure {
     pub strike: String,
     pub gamma_exposure: f64,
+    pub call_exposure: f64,
+    pub put_exposure: f64,
+    pub percent_of_total: f64,
+    pub open_interest: u64,
+    pub volume: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub struct GammaProfilePoint {
+    pub price: f64,
+    pub gamma_exposure: f64,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
 pub struct GammaExposureStats {
+    /// Deprecated alias of `computed_at`.
+    #[graphql(deprecation = "Use computedAt")]
     pub timestamp: String,
     pub symbol: String,
     pub prices: Vec<GammaExposure>,
+    pub total_exposure: f64,
     pub average_absolute_exposure: f64,
     pub average_positive_exposure: f64,
     pub average_negative_exposure: f64,
@@ -24,6 +38,20 @@ pub struct GammaExposureStats {
     pub weighted_average_negative_price: f64,
     pub absolute_maximum_price: f64,
     pub absolute_minimum_price: f64,
+    /// Strikes averaged by absolute net exposure, so large positive and
+    /// negative strikes both pull it towards them. Zero without exposure.
+    pub gamma_center: f64,
+    /// Strike with the largest positive net exposure.
+    pub call_wall: f64,
+    /// Strike with the most negative net exposure.
+    pub put_wall: f64,
+    pub gamma_flip_point: Option<f64>,
+    pub spot_price: Option<f64>,
+    /// When these stats were computed.
+    pub computed_at: String,
+    /// When the option chain behind these stats was fetched, if the db
+    /// recorded it.
+    pub chain_timestamp: Option<String>,
 }
 
 #[cfg(test)]
@@ -36,16 +64,32 @@ impl GammaExposureStats {
                 GammaExposure {
                     strike: "1.0".to_string(),
                     gamma_exposure: 1.0,
+                    call_exposure: 1.0,
+                    put_exposure: 0.0,
+                    percent_of_total: 1.0 / 6.0,
+                    open_interest: 0,
+                    volume: 0,
                 },
                 GammaExposure {
                     strike: "2.0".to_string(),
                     gamma_exposure: 2.0,
+                    call_exposure: 2.0,
+                    put_exposure: 0.0,
+                    percent_of_total: 2.0 / 6.0,
+                    open_interest: 0,
+                    volume: 0,
                 },
                 GammaExposure {
                     strike: "3.0".to_string(),
                     gamma_exposure: 3.0,
+                    call_exposure: 3.0,
+                    put_exposure: 0.0,
+                    percent_of_total: 3.0 / 6.0,
+                    open_interest: 0,
+                    volume: 0,
                 },
             ],
+            total_exposure: 6.0,
             average_absolute_exposure: 1.0,
             average_positive_exposure: 2.0,
             average_negative_exposure: 3.0,
@@ -58,6 +102,13 @@ impl GammaExposureStats {
             weighted_average_negative_price: 10.0,
             absolute_maximum_price: 11.0,
             absolute_minimum_price: 12.0,
+            gamma_center: 13.0,
+            call_wall: 3.0,
+            put_wall: 0.0,
+            gamma_flip_point: None,
+            spot_price: None,
+            computed_at: chrono::Utc::now().to_rfc3339(),
+            chain_timestamp: None,
         }
     }
 }
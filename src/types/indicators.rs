@@ -0,0 +1,17 @@
+use async_graphql::SimpleObject;
+use serde::{Deserialize, Serialize};
+
+/// One value of an indicator series, aligned with the bar at `time`.
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub struct IndicatorPoint {
+    pub time: String,
+    pub value: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub struct BollingerPoint {
+    pub time: String,
+    pub lower: Option<f64>,
+    pub middle: Option<f64>,
+    pub upper: Option<f64>,
+}
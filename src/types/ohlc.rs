@@ -26,6 +26,14 @@ pub enum OhlcInterval {
     FiveMinute,
     #[graphql(name = "15min")]
     FifteenMinute,
+    #[graphql(name = "hourly")]
+    Hourly,
+    #[graphql(name = "daily")]
+    Daily,
+    #[graphql(name = "weekly")]
+    Weekly,
+    #[graphql(name = "monthly")]
+    Monthly,
 }
 
 impl std::fmt::Display for OhlcInterval {
@@ -37,6 +45,10 @@ impl std::fmt::Display for OhlcInterval {
             OneMinute => "1min",
             FiveMinute => "5min",
             FifteenMinute => "15min",
+            Hourly => "hourly",
+            Daily => "daily",
+            Weekly => "weekly",
+            Monthly => "monthly",
         };
 
         write!(f, "{}", s)
@@ -52,7 +64,35 @@ impl FromStr for OhlcInterval {
             "1min" => OhlcInterval::OneMinute,
             "5min" => OhlcInterval::FiveMinute,
             "15min" => OhlcInterval::FifteenMinute,
+            "hourly" => OhlcInterval::Hourly,
+            "daily" => OhlcInterval::Daily,
+            "weekly" => OhlcInterval::Weekly,
+            "monthly" => OhlcInterval::Monthly,
             _ => anyhow::bail!("Invalid interval: {}", s),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval_round_trip() {
+        use OhlcInterval::*;
+
+        for interval in &[
+            Tick,
+            OneMinute,
+            FiveMinute,
+            FifteenMinute,
+            Hourly,
+            Daily,
+            Weekly,
+            Monthly,
+        ] {
+            assert_eq!(*interval, interval.to_string().parse().unwrap());
+        }
+        assert!("2min".parse::<OhlcInterval>().is_err());
+    }
+}
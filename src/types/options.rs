@@ -19,6 +19,8 @@ pub struct OptionInfo {
     pub high: Option<f64>,
     pub low: Option<f64>,
     pub close: Option<f64>,
+    pub bid: Option<f64>,
+    pub ask: Option<f64>,
     pub bid_iv: Option<f64>,
     pub mid_iv: Option<f64>,
     pub ask_iv: Option<f64>,
@@ -131,6 +133,8 @@ impl OptionInfo {
             high: Some(7.0),
             low: Some(8.0),
             close: Some(9.0),
+            bid: Some(1.0),
+            ask: Some(1.1),
             bid_iv: Some(15.0),
             mid_iv: Some(18.0),
             ask_iv: Some(16.0),
@@ -12,3 +12,13 @@ pub struct Quote {
     pub low: Option<f64>,
     pub close: Option<f64>,
 }
+
+/// One symbol's outcome in a batch quote request. Exactly one of `quote` and
+/// `error` is set.
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub struct QuoteResult {
+    pub symbol: String,
+    pub quote: Option<Quote>,
+    pub error: Option<String>,
+    pub error_code: Option<String>,
+}
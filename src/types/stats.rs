@@ -1,10 +1,24 @@
 use async_graphql::SimpleObject;
 use serde::{Deserialize, Serialize};
 
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub struct OptionStats {
+    pub strikes: Vec<StrikeStats>,
+    pub max_pain: f64,
+    pub put_call_ratio: Option<f64>,
+    pub put_call_volume_ratio: Option<f64>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
 pub struct StrikeStats {
     pub strike: f64,
     pub open_interest: u64,
+    pub volume: u64,
+    pub bid: Option<f64>,
+    pub ask: Option<f64>,
+    pub spread: Option<f64>,
+    pub spread_percent: Option<f64>,
+    pub illiquid: bool,
     pub call_exposure: HedgeExposure,
     pub put_exposure: HedgeExposure,
 }
@@ -15,3 +29,20 @@ pub struct HedgeExposure {
     pub vanna: f64,
     pub charm: f64,
 }
+
+/// Open interest at a strike on the later date and how it moved since the
+/// earlier one.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, SimpleObject)]
+pub struct OpenInterestChange {
+    pub strike: f64,
+    pub open_interest: u64,
+    pub change: i64,
+    pub call_change: i64,
+    pub put_change: i64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub struct TermStructurePoint {
+    pub expiration_date: String,
+    pub iv: f64,
+}
@@ -0,0 +1,11 @@
+use async_graphql::SimpleObject;
+use serde::{Deserialize, Serialize};
+
+/// What the db holds for a symbol. `last_updated` is the RFC 3339 fetch time
+/// of the latest option chain.
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub struct SymbolInfo {
+    pub symbol: String,
+    pub last_updated: Option<String>,
+    pub has_options: bool,
+}
@@ -1,3 +1,5 @@
+pub mod export;
+
 use std::convert::TryFrom;
 
 use serde::{Deserialize, Deserializer};
@@ -0,0 +1,28 @@
+use crate::types::GammaExposureStats;
+
+/// One `strike,gamma_exposure` row per price, with a header row.
+pub fn gamma_exposure_csv(stats: &GammaExposureStats) -> anyhow::Result<String> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record(["strike", "gamma_exposure"])?;
+    for price in &stats.prices {
+        writer.write_record([price.strike.clone(), price.gamma_exposure.to_string()])?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gamma_exposure_csv() {
+        let mut stats = GammaExposureStats::test();
+        stats.prices[2].strike = "3,000".to_string();
+        stats.prices[2].gamma_exposure = -2.5;
+
+        assert_eq!(
+            "strike,gamma_exposure\n1.0,1\n2.0,2\n\"3,000\",-2.5\n",
+            gamma_exposure_csv(&stats).unwrap()
+        );
+    }
+}